@@ -0,0 +1,178 @@
+use std::fmt;
+
+use crate::Chip8;
+
+// operands as written in the source, the same spelling disassemble() produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    V(u16),    // V0-VF
+    Num(u16),  // decimal or 0x hex
+    I,         // I
+    IndirectI, // [I]
+    Delay,     // DT
+    Sound,     // ST
+    Key,       // K
+    Font,      // F
+    Bcd,       // B
+    Flags,     // R, the SUPER-CHIP flag registers
+}
+
+// where assembling stopped, line and column count from 1 and the column is where the
+// instruction starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid assembly at line {}, column {}: {}",
+            self.line, self.column, self.reason
+        )
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// assembles one instruction per line in the syntax disassemble() prints, e.g.
+// `LD V1, 200`, `ADD V1, V2`, `DRW V0, V1, 5`, `JP 0x204` or `DW 0x1234` for raw data
+// `;` starts a comment, labels aren't supported so jumps take plain addresses
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError> {
+    let mut out = Vec::new();
+    for (idx, raw_line) in src.lines().enumerate() {
+        let line = raw_line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let opcode = assemble_line(line).map_err(|reason| AsmError {
+            line: idx + 1,
+            column: raw_line.len() - raw_line.trim_start().len() + 1,
+            reason,
+        })?;
+        out.extend_from_slice(&opcode.to_be_bytes());
+    }
+    Ok(out)
+}
+
+fn assemble_line(line: &str) -> Result<u16, String> {
+    let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let operands = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',')
+            .map(|op| parse_operand(op.trim()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    use Operand::*;
+    let addr = |nnn: u16| -> Result<u16, String> {
+        if nnn > 0xFFF {
+            return Err(format!("address {:#x} doesn't fit in 12 bits", nnn));
+        }
+        Ok(nnn)
+    };
+    let byte = |nn: u16| -> Result<u16, String> {
+        if nn > 0xFF {
+            return Err(format!("{} doesn't fit in a byte", nn));
+        }
+        Ok(nn)
+    };
+
+    let opcode = match (mnemonic.to_ascii_uppercase().as_str(), operands.as_slice()) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SCD", [Num(n)]) if *n <= 0xF => 0x00C0 | n,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("SPEED", [Num(nn)]) => 0x0100 | byte(*nn)?,
+        ("SYS", [Num(nnn)]) => addr(*nnn)?,
+        ("JP", [Num(nnn)]) => 0x1000 | addr(*nnn)?,
+        ("JP", [V(0), Num(nnn)]) => 0xB000 | addr(*nnn)?,
+        ("CALL", [Num(nnn)]) => 0x2000 | addr(*nnn)?,
+        ("SE", [V(x), Num(nn)]) => 0x3000 | x << 8 | byte(*nn)?,
+        ("SNE", [V(x), Num(nn)]) => 0x4000 | x << 8 | byte(*nn)?,
+        ("SE", [V(x), V(y)]) => 0x5000 | x << 8 | y << 4,
+        ("LD", [V(x), Num(nn)]) => 0x6000 | x << 8 | byte(*nn)?,
+        ("ADD", [V(x), Num(nn)]) => 0x7000 | x << 8 | byte(*nn)?,
+        ("LD", [V(x), V(y)]) => 0x8000 | x << 8 | y << 4,
+        ("OR", [V(x), V(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", [V(x), V(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", [V(x), V(y)]) => 0x8003 | x << 8 | y << 4,
+        ("ADD", [V(x), V(y)]) => 0x8004 | x << 8 | y << 4,
+        ("SUB", [V(x), V(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SHR", [V(x)]) => 0x8006 | x << 8,
+        ("SHR", [V(x), V(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SUBN", [V(x), V(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHL", [V(x)]) => 0x800E | x << 8,
+        ("SHL", [V(x), V(y)]) => 0x800E | x << 8 | y << 4,
+        ("SNE", [V(x), V(y)]) => 0x9000 | x << 8 | y << 4,
+        ("LD", [I, Num(nnn)]) => 0xA000 | addr(*nnn)?,
+        ("RND", [V(x), Num(nn)]) => 0xC000 | x << 8 | byte(*nn)?,
+        ("DRW", [V(x), V(y), Num(n)]) if *n <= 0xF => 0xD000 | x << 8 | y << 4 | n,
+        ("SKP", [V(x)]) => 0xE09E | x << 8,
+        ("SKNP", [V(x)]) => 0xE0A1 | x << 8,
+        ("LD", [V(x), Delay]) => 0xF007 | x << 8,
+        ("LD", [V(x), Key]) => 0xF00A | x << 8,
+        ("LD", [Delay, V(x)]) => 0xF015 | x << 8,
+        ("LD", [Sound, V(x)]) => 0xF018 | x << 8,
+        ("ADD", [I, V(x)]) => 0xF01E | x << 8,
+        ("LD", [Font, V(x)]) => 0xF029 | x << 8,
+        ("LD", [Bcd, V(x)]) => 0xF033 | x << 8,
+        ("LD", [IndirectI, V(x)]) => 0xF055 | x << 8,
+        ("LD", [V(x), IndirectI]) => 0xF065 | x << 8,
+        ("LD", [Flags, V(x)]) => 0xF075 | x << 8,
+        ("LD", [V(x), Flags]) => 0xF085 | x << 8,
+        ("DW", [Num(word)]) => *word,
+        _ => return Err(format!("can't assemble '{}'", line)),
+    };
+    Ok(opcode)
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    let upper = text.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Delay,
+        "ST" => Operand::Sound,
+        "K" => Operand::Key,
+        "F" => Operand::Font,
+        "B" => Operand::Bcd,
+        "R" => Operand::Flags,
+        _ => {
+            if let Some(reg) = upper.strip_prefix('V') {
+                u16::from_str_radix(reg, 16)
+                    .ok()
+                    .filter(|&reg| reg < 16)
+                    .map(Operand::V)
+                    .ok_or_else(|| format!("'{}' is not a register V0-VF", text))?
+            } else {
+                parse_number(&upper)
+                    .map(Operand::Num)
+                    .ok_or_else(|| format!("'{}' is not a number", text))?
+            }
+        }
+    };
+    Ok(operand)
+}
+
+fn parse_number(text: &str) -> Option<u16> {
+    match text.strip_prefix("0X") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+impl Chip8 {
+    // assembles src (see assemble) and loads it like load_rom
+    // nothing is loaded if the source has an error
+    pub fn load_asm(&mut self, src: &str) -> Result<(), AsmError> {
+        let program = assemble(src)?;
+        self.load_rom(&program);
+        Ok(())
+    }
+}
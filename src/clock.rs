@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// time source for update(), lets native, web and tests drive timing the same way
+pub trait Clock {
+    // milliseconds since some fixed starting point, only differences are used
+    fn now_ms(&self) -> f64;
+}
+
+// wall clock time, std::time::Instant isn't available on the web so this is native only
+#[cfg(not(target_arch = "wasm32"))]
+pub struct InstantClock {
+    start: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl InstantClock {
+    pub fn new() -> Self {
+        InstantClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for InstantClock {
+    fn now_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+// a clock that only moves when told to, for tests and anything else that must not depend
+// on real time, clones share the same reading so keep one to advance after set_clock
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now_ms: Arc<Mutex<f64>>, // shared so the emulator stays Send
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, elapsed: Duration) {
+        *self.now_ms.lock().unwrap() += elapsed.as_secs_f64() * 1000.0;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> f64 {
+        *self.now_ms.lock().unwrap()
+    }
+}
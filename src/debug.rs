@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Chip8, Chip8Error, FONT_SET, FONT_START_ADDR, StepOutcome};
+
+// One entry per thing that differs between two emulators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDiff {
+    Register { index: usize, left: u8, right: u8 },
+    Pc { left: u16, right: u16 },
+    Index { left: u16, right: u16 },
+    Sp { left: u16, right: u16 },
+    Stack { index: usize, left: u16, right: u16 },
+    DelayTimer { left: u8, right: u8 },
+    SoundTimer { left: u8, right: u8 },
+    Keypad { key: usize, left: bool, right: bool },
+    Ram { start: usize, end: usize },     // end is exclusive
+    Display { start: usize, end: usize }, // pixel indices, end is exclusive
+}
+
+impl Chip8 {
+    pub fn diff(&self, other: &Chip8) -> Vec<StateDiff> {
+        let mut diffs = Vec::new();
+
+        for (index, (&left, &right)) in self.vx.iter().zip(other.vx.iter()).enumerate() {
+            if left != right {
+                diffs.push(StateDiff::Register { index, left, right });
+            }
+        }
+        if self.pc != other.pc {
+            diffs.push(StateDiff::Pc {
+                left: self.pc,
+                right: other.pc,
+            });
+        }
+        if self.i != other.i {
+            diffs.push(StateDiff::Index {
+                left: self.i,
+                right: other.i,
+            });
+        }
+        if self.sp != other.sp {
+            diffs.push(StateDiff::Sp {
+                left: self.sp,
+                right: other.sp,
+            });
+        }
+        for (index, (&left, &right)) in self.stack.iter().zip(other.stack.iter()).enumerate() {
+            if left != right {
+                diffs.push(StateDiff::Stack { index, left, right });
+            }
+        }
+        if self.delay_timer != other.delay_timer {
+            diffs.push(StateDiff::DelayTimer {
+                left: self.delay_timer,
+                right: other.delay_timer,
+            });
+        }
+        if self.sound_timer != other.sound_timer {
+            diffs.push(StateDiff::SoundTimer {
+                left: self.sound_timer,
+                right: other.sound_timer,
+            });
+        }
+        for (key, (&left, &right)) in self.keypad.iter().zip(other.keypad.iter()).enumerate() {
+            if left != right {
+                diffs.push(StateDiff::Keypad { key, left, right });
+            }
+        }
+
+        // ram and display are big so report runs of differing bytes instead of every byte
+        for (start, end) in differing_ranges(&self.ram, &other.ram) {
+            diffs.push(StateDiff::Ram { start, end });
+        }
+        for (start, end) in differing_ranges(&self.display, &other.display) {
+            diffs.push(StateDiff::Display { start, end });
+        }
+
+        diffs
+    }
+}
+
+fn differing_ranges(left: &[u8], right: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut run_start: Option<usize> = None;
+    for (idx, (a, b)) in left.iter().zip(right.iter()).enumerate() {
+        match (a != b, run_start) {
+            (true, None) => run_start = Some(idx),
+            (false, Some(start)) => {
+                ranges.push((start, idx));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        ranges.push((start, left.len().min(right.len())));
+    }
+    ranges
+}
+
+// something step_n keeps an eye on, it stops right after an instruction changes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WatchSpec {
+    Register(u8), // V0-VF
+    Memory(u16),  // one byte of ram, wraps like the rest of ram access
+}
+
+// what a single step did, for debuggers and traces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepInfo {
+    pub pc: u16, // address the opcode was fetched from
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub outcome: StepOutcome,
+}
+
+impl Chip8 {
+    // executes one instruction and describes it
+    pub fn step_info(&mut self) -> Result<StepInfo, Chip8Error> {
+        let pc = self.pc;
+        let opcode = self.peek_opcode(pc);
+        let outcome = self.step()?;
+        Ok(StepInfo {
+            pc,
+            opcode,
+            mnemonic: disassemble(opcode),
+            outcome,
+        })
+    }
+
+    // runs up to n instructions and returns what each did
+    // stops early before a breakpoint (unless it's the first instruction, so you can resume
+    // from one), when the program halts on itself (JP to self or FX0A waiting), when it ran
+    // off into empty memory, after an instruction that changed a watchpoint, or after an
+    // unknown opcode with break on unknown set, a failed step returns its error instead
+    pub fn step_n(&mut self, n: usize) -> Result<Vec<StepInfo>, Chip8Error> {
+        let mut trace = Vec::new();
+        for step in 0..n {
+            if step > 0 && self.breakpoints.contains(&self.pc) {
+                break;
+            }
+            let watched = self.watched_values();
+            let info = self.step_info()?;
+            let halted = self.pc == info.pc || self.is_runaway();
+            let unknown = matches!(info.outcome, StepOutcome::UnknownOpcode(_));
+            trace.push(info);
+            if halted || unknown || self.watched_values() != watched {
+                break;
+            }
+        }
+        Ok(trace)
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    // in address order
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    pub fn add_watchpoint(&mut self, spec: WatchSpec) {
+        self.watchpoints.insert(spec);
+    }
+
+    pub fn remove_watchpoint(&mut self, spec: WatchSpec) {
+        self.watchpoints.remove(&spec);
+    }
+
+    // registers first, then memory in address order
+    pub fn watchpoints(&self) -> Vec<WatchSpec> {
+        self.watchpoints.iter().copied().collect()
+    }
+
+    // removes every breakpoint and watchpoint, for a debugger's "clear all" button
+    pub fn clear_all_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
+
+    fn watched_values(&self) -> Vec<u8> {
+        self.watchpoints
+            .iter()
+            .map(|spec| match *spec {
+                WatchSpec::Register(index) => self.vx[index as usize & 0xF],
+                WatchSpec::Memory(addr) => self.ram[addr as usize % self.ram.len()],
+            })
+            .collect()
+    }
+}
+
+// one executed instruction in a reference trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+// where a run first stopped matching its reference trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    pub cycle: u64, // instructions executed before the mismatching one
+    pub expected: TraceEntry,
+    pub actual: TraceEntry,
+}
+
+// a trace as text, one `<pc> <opcode>` pair of hex numbers per line, e.g. `0200 6001`
+// blank lines and `#` comments are skipped
+pub fn parse_trace(text: &str) -> Result<Vec<TraceEntry>, Chip8Error> {
+    let mut trace = Vec::new();
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |reason: String| Chip8Error::InvalidTrace {
+            line: idx + 1,
+            reason,
+        };
+        let mut fields = line.split_whitespace().map(|field| {
+            let digits = field.trim_start_matches("0x");
+            u16::from_str_radix(digits, 16)
+                .map_err(|_| invalid(format!("'{}' is not a hex number", field)))
+        });
+        let (Some(pc), Some(opcode), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(invalid(format!("expected `<pc> <opcode>`, got '{}'", line)));
+        };
+        trace.push(TraceEntry {
+            pc: pc?,
+            opcode: opcode?,
+        });
+    }
+    Ok(trace)
+}
+
+impl Chip8 {
+    // runs in lockstep with a trace from a known good emulator, checking pc and opcode
+    // before every instruction, returns the first mismatch or None if the whole trace matched
+    pub fn compare_trace(
+        &mut self,
+        reference: &[TraceEntry],
+    ) -> Result<Option<Divergence>, Chip8Error> {
+        for &expected in reference {
+            let actual = TraceEntry {
+                pc: self.pc,
+                opcode: self.peek_opcode(self.pc),
+            };
+            if actual != expected {
+                return Ok(Some(Divergence {
+                    cycle: self.cycles,
+                    expected,
+                    actual,
+                }));
+            }
+            self.step()?;
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Reserved, // belonged to the original interpreter, unused here apart from the font
+    Font,
+    Program, // from the entry point up, where ROMs get loaded
+}
+
+// a labelled stretch of ram, end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u16,
+    pub end: u16,
+    pub kind: RegionKind,
+}
+
+impl Chip8 {
+    // how ram is laid out, in address order, so a hex view can colour each part
+    pub fn memory_regions(&self) -> Vec<MemoryRegion> {
+        let font_end = FONT_START_ADDR + FONT_SET.len();
+        let entry = self.entry_point as usize;
+        let region = |start: usize, end: usize, kind| MemoryRegion {
+            start: start as u16,
+            end: end as u16,
+            kind,
+        };
+        let mut regions = vec![
+            region(0, FONT_START_ADDR, RegionKind::Reserved),
+            region(FONT_START_ADDR, font_end, RegionKind::Font),
+        ];
+        if entry > font_end {
+            regions.push(region(font_end, entry, RegionKind::Reserved));
+        }
+        regions.push(region(
+            entry.max(font_end),
+            self.ram.len(),
+            RegionKind::Program,
+        ));
+        regions
+    }
+
+    // first and last address of the loaded program's code, for bounding a disassembly
+    // follows jumps, calls and skips from the entry point so sprite data after the code
+    // is left out, a BNNN makes the targets unknowable so then it's just the last
+    // nonzero byte, with nothing loaded both ends are the entry point
+    pub fn code_extent(&self) -> (u16, u16) {
+        let entry = self.entry_point;
+        let last_nonzero = self.ram[entry as usize..]
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(entry, |offset| entry + offset as u16);
+
+        let mut reached = BTreeSet::new();
+        let mut pending = vec![entry];
+        while let Some(addr) = pending.pop() {
+            if addr < entry || addr as usize + 1 >= self.ram.len() || !reached.insert(addr) {
+                continue;
+            }
+            let opcode = self.peek_opcode(addr);
+            let next = addr + 2;
+            match (opcode >> 12, opcode & 0xFF) {
+                _ if opcode == 0x0000 => {
+                    reached.remove(&addr); // empty memory, not code
+                }
+                _ if opcode == 0x00EE || opcode == 0x00FD => {} // RET and EXIT end the path
+                (0x1, _) => pending.push(opcode & 0x0FFF),
+                (0x2, _) => pending.extend([opcode & 0x0FFF, next]),
+                (0x3 | 0x4 | 0x5 | 0x9, _) | (0xE, 0x9E) | (0xE, 0xA1) => {
+                    pending.extend([next, next + 2])
+                }
+                (0xB, _) => return (entry, last_nonzero),
+                _ => pending.push(next),
+            }
+        }
+        match reached.last() {
+            Some(&last) => (entry, last + 1),
+            None => (entry, entry),
+        }
+    }
+
+    // every register by name, V0-VF, PC, I, SP, DT and ST, for inspectors that want
+    // the state as one JSON object, sorted by name so dumps diff cleanly
+    pub fn register_map(&self) -> BTreeMap<String, u16> {
+        let mut map: BTreeMap<String, u16> = self
+            .vx
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| (format!("V{:X}", index), value as u16))
+            .collect();
+        map.insert("PC".to_string(), self.pc);
+        map.insert("I".to_string(), self.i);
+        map.insert("SP".to_string(), self.sp);
+        map.insert("DT".to_string(), self.delay_timer as u16);
+        map.insert("ST".to_string(), self.sound_timer as u16);
+        map
+    }
+}
+
+// assembly text for an opcode, using the same mnemonics as the decode_execute comments
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    match ((opcode & 0xF000) >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => "CLS".to_string(),
+        (0x0, 0x0, 0xE, 0xE) => "RET".to_string(),
+        (0x0, 0x0, 0xC, _) => format!("SCD {}", n),
+        (0x0, 0x0, 0xF, 0xE) => "LOW".to_string(),
+        (0x0, 0x0, 0xF, 0xF) => "HIGH".to_string(),
+        (0x0, 0x1, _, _) => format!("SPEED 0x{:02X}", nn), // only with set_debug_extensions
+        (0x0, _, _, _) => format!("SYS 0x{:03X}", nnn),
+        (0x1, _, _, _) => format!("JP 0x{:03X}", nnn),
+        (0x2, _, _, _) => format!("CALL 0x{:03X}", nnn),
+        (0x3, _, _, _) => format!("SE V{:X}, 0x{:02X}", x, nn),
+        (0x4, _, _, _) => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, 0x{:02X}", x, nn),
+        (0x7, _, _, _) => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, 0x{:03X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, 0x{:03X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, 0x{:02X}", x, nn),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        (0xF, _, 0x7, 0x5) => format!("LD R, V{:X}", x),
+        (0xF, _, 0x8, 0x5) => format!("LD V{:X}, R", x),
+        _ => format!("DW 0x{:04X}", opcode), // not an instruction, show it as raw data
+    }
+}
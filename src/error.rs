@@ -0,0 +1,70 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    NoRomLoaded, // pc was never moved off 0, load_rom wasn't called
+    EntryBelowMinimum { pc: u16, minimum: u16 }, // pc points into the reserved interpreter area
+    InvalidKeyMap { line: usize, reason: String }, // keymap text couldn't be parsed
+    UnknownOpcode { opcode: u16, addr: u16 }, // only reported in strict mode
+    InvalidSaveState(String), // load_state got bytes it can't use
+    DisplaySizeMismatch { expected: usize, actual: usize }, // set_display buffer of the wrong size
+    ExecutingEmptyMemory { addr: u16 }, // strict mode, pc ran into zeroed ram
+    InvalidTrace { line: usize, reason: String }, // reference trace text couldn't be parsed
+    InvalidInputScript { entry: usize, reason: String }, // scripted key input couldn't be parsed
+    StackOverflow { addr: u16, depth: usize }, // CALL with all stack entries in use
+    StackUnderflow { addr: u16 }, // RET with nothing on the stack
+    RomTooSmall { len: usize }, // not even one instruction in the ROM
+    InvalidReplay(String), // a .c8replay bundle couldn't be read or parsed
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::NoRomLoaded => write!(f, "no ROM loaded, call load_rom before run"),
+            Chip8Error::EntryBelowMinimum { pc, minimum } => write!(
+                f,
+                "program counter {:#06x} is below the minimum entry point {:#06x}",
+                pc, minimum
+            ),
+            Chip8Error::InvalidKeyMap { line, reason } => {
+                write!(f, "invalid keymap on line {}: {}", line, reason)
+            }
+            Chip8Error::UnknownOpcode { opcode, addr } => {
+                write!(f, "unknown opcode {:#06x} at {:#06x}", opcode, addr)
+            }
+            Chip8Error::InvalidSaveState(reason) => write!(f, "invalid save state: {}", reason),
+            Chip8Error::DisplaySizeMismatch { expected, actual } => write!(
+                f,
+                "display buffer has {} pixels, expected {}",
+                actual, expected
+            ),
+            Chip8Error::ExecutingEmptyMemory { addr } => {
+                write!(f, "executing empty memory at {:#06x}", addr)
+            }
+            Chip8Error::InvalidTrace { line, reason } => {
+                write!(f, "invalid trace on line {}: {}", line, reason)
+            }
+            Chip8Error::InvalidInputScript { entry, reason } => {
+                write!(f, "invalid input script entry {}: {}", entry, reason)
+            }
+            Chip8Error::StackOverflow { addr, depth } => write!(
+                f,
+                "stack overflow at {:#06x}, all {} entries are in use",
+                addr, depth
+            ),
+            Chip8Error::StackUnderflow { addr } => {
+                write!(
+                    f,
+                    "stack underflow at {:#06x}, RET with an empty stack",
+                    addr
+                )
+            }
+            Chip8Error::RomTooSmall { len } => {
+                write!(f, "ROM is {} bytes, too small to hold an instruction", len)
+            }
+            Chip8Error::InvalidReplay(reason) => write!(f, "invalid replay: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
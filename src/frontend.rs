@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use crate::{Chip8, Chip8Error, FRAME_DURATION};
+
+// what run_with needs from a window, so the loop can also run against mocks in tests
+pub trait Renderer {
+    // buffer is width * height 0x00RRGGBB pixels, see to_rgba
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize);
+}
+
+pub trait InputSource {
+    // false ends the run loop
+    fn is_open(&self) -> bool;
+    // called once at the start of every frame, index is the chip-8 key
+    fn pressed_keys(&mut self) -> [bool; 16];
+    // true once each time the user asks to show or hide the stats overlay
+    fn stats_toggled(&mut self) -> bool {
+        false
+    }
+    // called at the end of every frame, whether or not something was presented
+    fn end_frame(&mut self) {}
+}
+
+pub trait AudioOutput {
+    fn set_beeping(&mut self, beeping: bool);
+}
+
+// pixel by pixel output for hardware like an LED matrix, where pushing a whole frame
+// is slow, see send_pixel_changes
+pub trait PixelSink {
+    fn set_pixel(&mut self, x: usize, y: usize, on: bool);
+}
+
+// how long the frame pacer sleeps after a frame that ran from frame_start_ms to
+// frame_end_ms (clock readings), zero once the frame took its whole 1/60s or more
+pub fn pacing_sleep(frame_start_ms: f64, frame_end_ms: f64) -> Duration {
+    let elapsed = Duration::from_secs_f64((frame_end_ms - frame_start_ms).max(0.0) / 1000.0);
+    FRAME_DURATION.saturating_sub(elapsed)
+}
+
+impl Chip8 {
+    // sleep away the rest of every frame in run_with instead of leaving pacing to the
+    // window, for platforms where minifb's frame limit busy waits and pins a core
+    // needs a clock, and only sleeps on native since the web can't block
+    pub fn set_frame_pacing(&mut self, enabled: bool) {
+        self.frame_pacing = enabled;
+    }
+
+    // tells the sink about every pixel that changed since the last call, in row order
+    // the first call, and the first after switching resolution, sends the whole screen
+    pub fn send_pixel_changes(&mut self, sink: &mut impl PixelSink) {
+        let (width, _) = self.dimensions();
+        let current = self.active_display();
+        let sent = self
+            .sent_pixels
+            .as_deref()
+            .filter(|sent| sent.len() == current.len());
+        for (idx, &pixel) in current.iter().enumerate() {
+            if sent.is_none_or(|sent| sent[idx] != pixel) {
+                sink.set_pixel(idx % width, idx / width, pixel == 1);
+            }
+        }
+        self.sent_pixels = Some(current.to_vec());
+    }
+
+    // the frame loop behind run(), one update() per iteration until the input source
+    // closes, so emulation speed follows the clock the same way tick_with_dt does on
+    // the web whatever rate the window refreshes at, without a clock it falls back to
+    // one run_frame per iteration, fps is measured with the clock too
+    pub fn run_with<F: Renderer + InputSource>(
+        &mut self,
+        frontend: &mut F,
+        audio: &mut impl AudioOutput,
+    ) -> Result<(), Chip8Error> {
+        self.check_entry()?;
+
+        let mut frames = 0;
+        let mut fps_window_start = self.clock.as_ref().map(|clock| clock.now_ms());
+        // the first frame runs the time since the loop started, not since an old update()
+        self.last_update_ms = fps_window_start;
+
+        while frontend.is_open() {
+            let frame_start = self.clock.as_ref().map(|clock| clock.now_ms());
+
+            // 1. Update Keypad state
+            for (key, pressed) in frontend.pressed_keys().into_iter().enumerate() {
+                self.set_key(key as u8, pressed);
+            }
+            if frontend.stats_toggled() {
+                self.show_stats = !self.show_stats;
+                self.draw_flag = true;
+            }
+
+            // 2. Run the CPU cycles and timer ticks for the time since the last frame
+            // (At 60 FPS, 10 cycles per frame = 600Hz)
+            if self.clock.is_some() {
+                self.update()?;
+            } else {
+                self.run_frame()?;
+            }
+
+            // 3. Gate the beep on the sound timer
+            audio.set_beeping(self.is_beeping());
+
+            // 4. Update Window Buffer
+            // the stats overlay changes every frame so it forces a redraw
+            if self.draw_flag || self.show_stats {
+                let buffer = self.to_rgba();
+                let (width, height) = self.dimensions();
+                frontend.present(&buffer, width, height);
+                self.mark_drawn();
+            }
+            frontend.end_frame();
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.frame_pacing
+                && let (Some(start), Some(clock)) = (frame_start, &self.clock)
+            {
+                std::thread::sleep(pacing_sleep(start, clock.now_ms()));
+            }
+
+            frames += 1;
+            let now = self.clock.as_ref().map(|clock| clock.now_ms());
+            if let (Some(start), Some(now)) = (fps_window_start, now)
+                && now - start >= 1000.0
+            {
+                self.fps = frames;
+                frames = 0;
+                fps_window_start = Some(now);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{Chip8, read_opcode};
+
+// an opcode split into the fields decode_execute matches on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    pub opcode: u16,
+    pub primary: u16,
+    pub x: usize,
+    pub y: usize,
+    pub n: u8,
+    pub nn: u8,
+    pub nnn: u16,
+}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Self {
+        Instruction {
+            opcode,
+            primary: (opcode & 0xF000) >> 12, // 0x0FFF is mask to just extract pos 12-15
+            x: ((opcode & 0x0F00) >> 8) as usize,
+            y: ((opcode & 0x00F0) >> 4) as usize,
+            n: (opcode & 0x000F) as u8,
+            nn: (opcode & 0x00FF) as u8,
+            nnn: opcode & 0x0FFF,
+        }
+    }
+}
+
+// the loaded program decoded ahead of time, plus one bit per ram byte that was written
+// since, instructions touching a written byte are decoded again on fetch
+pub(crate) struct InstructionCache {
+    start: usize,
+    entries: Vec<Instruction>, // entries[k] is the opcode at start + k
+    dirty: [u64; 4096 / 64],
+}
+
+impl InstructionCache {
+    fn build(ram: &[u8], start: usize, len: usize) -> Self {
+        let end = (start + len).min(ram.len());
+        let entries = (start..end)
+            .map(|addr| Instruction::decode(read_opcode(ram, addr)))
+            .collect();
+        InstructionCache {
+            start,
+            entries,
+            dirty: [0; 4096 / 64],
+        }
+    }
+
+    fn is_dirty(&self, addr: usize) -> bool {
+        addr < 4096 && self.dirty[addr / 64] & (1 << (addr % 64)) != 0
+    }
+
+    pub fn mark_dirty(&mut self, addr: usize) {
+        if addr < 4096 {
+            self.dirty[addr / 64] |= 1 << (addr % 64);
+        }
+    }
+
+    // the last byte of the program is left out, its opcode's low byte lies past the end
+    pub fn get(&self, addr: usize) -> Option<Instruction> {
+        if addr < self.start || self.is_dirty(addr) || self.is_dirty(addr + 1) {
+            return None;
+        }
+        let entry = self.entries.get(addr - self.start)?;
+        (addr + 1 < self.start + self.entries.len()).then_some(*entry)
+    }
+}
+
+impl Chip8 {
+    // experimental, decodes the loaded program once instead of on every fetch
+    // ram written by instructions is tracked so self modifying code still works, after
+    // writing to `ram` directly call invalidate_instruction_cache
+    pub fn set_instruction_cache(&mut self, enabled: bool) {
+        self.use_instruction_cache = enabled;
+        self.invalidate_instruction_cache();
+    }
+
+    pub fn invalidate_instruction_cache(&mut self) {
+        self.instruction_cache = self
+            .use_instruction_cache
+            .then(|| InstructionCache::build(&self.ram, self.entry_point as usize, self.rom_len));
+    }
+}
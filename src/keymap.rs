@@ -0,0 +1,157 @@
+use minifb::Key;
+use std::fmt;
+
+use crate::Chip8Error;
+
+// keyboard keys a chip-8 key can be bound to
+// Escape and F1 are left out since the run loop uses them
+const BINDABLE_KEYS: [Key; 68] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Space,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Comma,
+    Key::Period,
+    Key::Slash,
+    Key::Semicolon,
+    Key::Apostrophe,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::Minus,
+    Key::Equal,
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadDot,
+];
+
+// which keyboard key drives each of the 16 chip-8 keys, indexed by chip-8 key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMap {
+    pub keys: [Key; 16],
+}
+
+impl Default for KeyMap {
+    // the Pong friendly layout the emulator always shipped with
+    fn default() -> Self {
+        KeyMap {
+            keys: [
+                Key::X,    // 0
+                Key::W,    // 1 player 1 up
+                Key::Key2, // 2
+                Key::Key3, // 3
+                Key::Q,    // 4 player 1 down
+                Key::W,    // 5
+                Key::E,    // 6
+                Key::A,    // 7
+                Key::S,    // 8
+                Key::D,    // 9
+                Key::Z,    // A
+                Key::C,    // B
+                Key::K,    // C player 2 up
+                Key::J,    // D player 2 down
+                Key::F,    // E
+                Key::V,    // F
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    // one `<hex key>=<key name>` binding per line, blank lines and `#` comments are skipped
+    // keys that aren't mentioned keep their default binding
+    pub fn parse(text: &str) -> Result<KeyMap, Chip8Error> {
+        let mut map = KeyMap::default();
+        for (idx, raw_line) in text.lines().enumerate() {
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: String| Chip8Error::InvalidKeyMap {
+                line: idx + 1,
+                reason,
+            };
+
+            let (chip8_key, key_name) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected `<key>=<name>`, got '{}'", line)))?;
+            let chip8_key = chip8_key.trim();
+            let slot = u8::from_str_radix(chip8_key, 16)
+                .ok()
+                .filter(|&k| k < 16)
+                .ok_or_else(|| invalid(format!("'{}' is not a chip-8 key 0-F", chip8_key)))?;
+            let key_name = key_name.trim();
+            let key = key_from_name(key_name)
+                .ok_or_else(|| invalid(format!("unknown key name '{}'", key_name)))?;
+            map.keys[slot as usize] = key;
+        }
+        Ok(map)
+    }
+}
+
+impl fmt::Display for KeyMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (slot, key) in self.keys.iter().enumerate() {
+            writeln!(f, "{:X}={:?}", slot, key)?;
+        }
+        Ok(())
+    }
+}
+
+// key names are the minifb variant names, e.g. `W`, `Key2` or `NumPad5`
+fn key_from_name(name: &str) -> Option<Key> {
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key| format!("{:?}", key) == name)
+}
@@ -1,13 +1,53 @@
 #[cfg(not(target_arch = "wasm32"))]
-use minifb::{Key, Window};
-use rand::Rng;
+use minifb::{Key, KeyRepeat, Scale, Window};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::time::Duration;
 #[cfg(not(target_arch = "wasm32"))]
 use rodio::Sink;
 
+pub mod asm;
+pub mod clock;
+pub mod debug;
+mod error;
+pub mod frontend;
+mod icache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod keymap;
+pub mod opcodes;
+#[cfg(not(target_arch = "wasm32"))]
+mod persist;
+pub mod quirks;
+pub mod render;
+pub mod replay;
+mod rng;
+pub mod roms;
+pub mod script;
+mod state;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
 
-const FONT_SET: [u8; 80] = [
+pub use asm::AsmError;
+#[cfg(not(target_arch = "wasm32"))]
+pub use clock::InstantClock;
+pub use clock::{Clock, ManualClock};
+pub use debug::{
+    Divergence, MemoryRegion, RegionKind, StateDiff, StepInfo, TraceEntry, WatchSpec, disassemble,
+    parse_trace,
+};
+pub use error::Chip8Error;
+pub use frontend::{AudioOutput, InputSource, PixelSink, Renderer, pacing_sleep};
+#[cfg(not(target_arch = "wasm32"))]
+pub use keymap::KeyMap;
+pub use opcodes::{OpcodeFamily, OpcodeGroup, opcode_group};
+pub use quirks::{Platform, Quirks};
+pub use replay::Replay;
+pub use script::{InputScript, ScriptEvent};
+use icache::{Instruction, InstructionCache};
+use rng::{SplitMix64, VipRandom};
+
+pub const FONT_SET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0  like ASCII those bits are high
     /* ****
      *  *
@@ -33,24 +73,174 @@ const FONT_SET: [u8; 80] = [
 ];
 
 //not from 0 as convention historical reasons
-const FONT_START_ADDR: usize = 0x050;
+pub const FONT_START_ADDR: usize = 0x050;
+pub const FONT_GLYPH_SIZE: usize = 5; // each glyph is 5 rows of one byte
+// the physical 4x4 keypad of the COSMAC VIP, row by row
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+// screen size in pixels, SUPER-CHIP adds a hires mode at twice the resolution
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+pub const HI_WIDTH: usize = 128;
+pub const HI_HEIGHT: usize = 64;
+// 4KB of ram means 12 bit addresses, anything computed past the top wraps back to 0
+const ADDR_MASK: usize = 0xFFF;
+// programs start here on the COSMAC VIP, the ETI-660 loads them at 0x600 instead
+pub const DEFAULT_ENTRY_POINT: u16 = 0x200;
+pub const ETI_660_ENTRY_POINT: u16 = 0x600;
 pub struct Chip8 {
     //first 0x000 to 0x1FF is reserved
     pub ram: [u8; 4096], // 2n = 4096 means 12 bits required to address a location(we take max)
     pub pc: u16,         // we have to take u16 to accommodate 12 bits
     i: u16,              //index register not instruction register it's for drawing sprites
     pub vx: [u8; 16],    // v0..vE is general purpose vF is for flag
-    pub display: [u8; 64 * 32],
+    pub display: [u8; HI_WIDTH * HI_HEIGHT], // only the first width * height pixels are in use
+    hires: bool,
     pub draw_flag: bool,
-    pub stack: [u16; 16], //store return address and can only be 16 deep
+    pub stack: Vec<u16>,  //store return addresses, as many entries as set_stack_size allows
     pub sp: u16,          // index to current entry in stack
     pub keypad: [bool; 16], //buffer that holds keys for specific key binds which is for moving
     delay_timer: u8,
     sound_timer: u8,
+    entry_point: u16, // where load_rom puts the program and the lowest pc run accepts
+    cycles: u64,      // total instructions executed
+    fps: u32,         // frames shown in the last second, measured by the front-end
+    show_stats: bool, // draw the fps/cycle readout on top of the rgba output
+    cycles_per_tick: usize,   // instructions run per 60Hz frame
+    auto_idle_throttle: bool, // run fewer instructions per frame while the game is idle waiting
+    idle: bool, // pc stayed put (FX0A wait or JP to self) or is going round a delay timer wait
+    timer_wait_loop: Option<(u16, u16)>, // first address and the JP of the loop pc is in
+    quirks: Quirks,
+    platform: Option<Platform>, // preset the quirks came from, None for the defaults
+    protect_font: bool,         // drop program writes into the built in font
+    timer_rate_hz: u32,         // how often DT and ST count down, 60 on real hardware
+    timer_accum: u128,          // leftover nanoseconds * hz that didn't add up to a full tick yet
+    cycle_accum: u128,          // same as timer_accum but for run_for's instruction clock
+    cycle_timed_timers: bool,   // tick timers from step() by instruction count, not per frame
+    timer_cycle_accum: u64,     // leftover cycles * hz for advance_timers_by_cycles
+    #[cfg(not(target_arch = "wasm32"))]
+    keymap: KeyMap, // keyboard bindings used by update_keypad
+    strict: bool,         // unknown opcodes stop step() with an error instead of being skipped
+    break_on_unknown: bool, // unknown opcodes pause with StepOutcome::UnknownOpcode
+    unknown_opcodes: u64, // how many unknown opcodes were skipped
+    unhandled: BTreeMap<u16, u64>, // each distinct unknown opcode and how often it came up
+    beep_threshold: u8,   // tone plays while sound_timer is above this
+    rng: SplitMix64,      // source for CXNN, part of save states
+    rng_algorithm: RngAlgorithm,
+    vip_rng: VipRandom,   // CXNN source for RngAlgorithm::CosmacLfsr, seeded by set_seed
+    crt_effect: bool,       // darken every other scanline in the rgba output
+    scanline_intensity: u8, // percent the odd scanlines are darkened by
+    crt_bloom: bool,        // glow around lit pixels while the crt effect is on
+    frame_blend: bool,      // mix the previous frame into to_rgba to hide XOR flicker
+    prev_display: [u8; HI_WIDTH * HI_HEIGHT], // display as it was when the frame started
+    breakpoints: BTreeSet<u16>, // step_n stops before these addresses
+    watchpoints: BTreeSet<WatchSpec>, // step_n stops after an instruction changes these
+    disabled_opcodes: HashSet<OpcodeGroup>, // decoded as unknown, see set_opcode_group_enabled
+    clock: Option<Box<dyn Clock + Send>>, // drives update(), the web build passes time in itself
+    last_update_ms: Option<f64>,   // clock reading at the previous update()
+    collision_since_last_check: bool, // latched by DXYN collisions, cleared by check_collision
+    zero_opcode_run: u32, // consecutive 0x0000 opcodes, a program that ran off its end
+    rpl_flags: [u8; 16],  // SUPER-CHIP flag registers, survive resets and can live on disk
+    rom_hash: Option<u64>, // identifies the loaded ROM for per game save files
+    rom_len: usize,        // bytes load_rom copied in, the instruction cache covers these
+    #[cfg(not(target_arch = "wasm32"))]
+    persistence_dir: Option<PathBuf>, // where the flag registers are saved, off when None
+    on_beep_start: Option<Box<dyn FnMut(u8) + Send>>, // called by FX18 when a beep starts
+    stack_overflowed: bool, // set by a CALL with a full stack, step turns it into an error
+    stack_underflowed: bool, // same for a RET with an empty stack
+    use_instruction_cache: bool,
+    instruction_cache: Option<InstructionCache>, // Some while use_instruction_cache is on
+    draw_mode: DrawMode,
+    fx0a_selection: Fx0aMode,
+    frame_pacing: bool, // run_with sleeps out each frame itself
+    sent_pixels: Option<Vec<u8>>, // active display as send_pixel_changes last passed it on
+    last_draw_bounds: Option<(u8, u8, u8, u8)>, // x, y, width, height of the latest DXYN
+    debug_extensions: bool, // non-standard opcodes for the emulator's own test ROMs
+    rng_log: Option<Vec<u8>>, // every random byte CXNN used, Some while logging is on
+    rng_replay: VecDeque<u8>, // random bytes handed out before the rng is asked again
+    rng_frozen: bool,         // CXNN gets FROZEN_RANDOM_BYTE and the rng is left alone
+    key_presses: u64,          // counts presses made through set_key
+    key_pressed_at: [u64; 16], // key_presses when each key last went down, 0 if never
+    queued_input: VecDeque<ScriptEvent>, // from queue_input, run_frame applies them in order
+    input_frame: u64,                    // frames run_frame has started since queue_input
+}
+
+// what the original interpreter allowed, extended ones sometimes go deeper
+pub const DEFAULT_STACK_SIZE: usize = 16;
+
+// where the VIP kept the page of interpreter code its CXNN routine reads, it's empty here
+// unless a program or the host puts something there
+const VIP_INTERPRETER_PAGE: std::ops::Range<usize> = 0x100..0x200;
+
+// what CXNN masks while the rng is frozen
+pub const FROZEN_RANDOM_BYTE: u8 = 0;
+
+// this many 0x0000s in a row means pc is walking through empty ram
+const RUNAWAY_ZERO_OPCODES: u32 = 2;
+
+// while idle only this many instructions run per frame, enough to notice the loop ending
+// a delay timer wait loop gets one pass through the loop instead
+const IDLE_CYCLES_PER_TICK: usize = 1;
+// longest `LD Vx, DT / SE Vx, 0 / JP loop` style wait that's treated as idle
+const MAX_TIMER_WAIT_INSTRUCTIONS: u16 = 4;
+// one 60Hz frame, rounded up so 60 of them always add up to at least a second
+pub(crate) const FRAME_DURATION: Duration = Duration::from_nanos(16_666_667);
+// run_for ignores time beyond this, a backgrounded browser tab can report seconds at once
+const MAX_RUN_FOR: Duration = Duration::from_millis(250);
+
+// performance readout for front-ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub fps: u32,
+    pub cycles: u64,
+}
+
+// what step() did when it didn't fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Executed,
+    UnknownOpcode(u16), // only with set_break_on_unknown, the run loops pause on it
+}
+
+// what step_frame did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameOutcome {
+    pub cycles: usize, // instructions executed
+    pub drew: bool,    // the screen changed during the frame
+    pub beeping: bool, // sound is on at the end of the frame
+}
+
+// which key FX0A takes when several are held, interpreters disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fx0aMode {
+    #[default]
+    LowestIndex, // scan 0 to F and take the first one down
+    MostRecent,  // the key pressed last, needs presses to go through set_key
+}
+
+// where CXNN gets its random bytes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RngAlgorithm {
+    #[default]
+    Modern, // SplitMix64
+    CosmacLfsr, // the COSMAC VIP interpreter's routine, mixes in ram 0x100-0x1FF like it did
+}
+
+// how DXYN combines sprite pixels with the screen, overlaps set VF either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawMode {
+    #[default]
+    Xor, // standard CHIP-8, drawing twice erases
+    Or,  // pixels are only ever turned on
 }
 
 impl Chip8 {
     pub fn tick_timers(&mut self) {
+        self.vip_rng.tick(); // the VIP's interrupt bumps the random seed every tick
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
@@ -59,6 +249,95 @@ impl Chip8 {
             self.sound_timer -= 1;
         }
     }
+
+    // counts the timers down for a stretch of real time at timer_rate_hz
+    // fractions of a tick carry over to the next call, returns how many ticks happened
+    pub fn advance_timers(&mut self, elapsed: Duration) -> u32 {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        self.timer_accum += elapsed.as_nanos() * self.timer_rate_hz as u128;
+        let ticks = (self.timer_accum / NANOS_PER_SEC) as u32;
+        self.timer_accum %= NANOS_PER_SEC;
+        for _ in 0..ticks {
+            self.tick_timers();
+        }
+        ticks
+    }
+
+    // same as advance_timers but the time is given as instructions at clock_hz()
+    pub fn advance_timers_by_cycles(&mut self, cycles: u64) -> u32 {
+        let clock_hz = self.clock_hz() as u64;
+        if clock_hz == 0 {
+            return 0;
+        }
+        self.timer_cycle_accum += cycles * self.timer_rate_hz as u64;
+        let ticks = (self.timer_cycle_accum / clock_hz) as u32;
+        self.timer_cycle_accum %= clock_hz;
+        for _ in 0..ticks {
+            self.tick_timers();
+        }
+        ticks
+    }
+
+    // how many instructions at clock_hz() until the next timer reaches zero, so a
+    // scheduler can sleep until then, None once both timers are already stopped
+    pub fn cycles_until_timer_event(&self) -> Option<u64> {
+        let ticks = [self.delay_timer, self.sound_timer]
+            .into_iter()
+            .filter(|&timer| timer > 0)
+            .min()? as u64;
+        let clock_hz = self.clock_hz() as u64;
+        let rate = self.timer_rate_hz as u64;
+        if clock_hz == 0 || rate == 0 {
+            return None;
+        }
+        // counts from the partial tick advance_timers_by_cycles has built up
+        let needed = (ticks * clock_hz).saturating_sub(self.timer_cycle_accum);
+        Some(needed.div_ceil(rate))
+    }
+
+    // when on every instruction moves the timers along, so a ROM that sets DT and reads it
+    // back a few instructions later sees it count down inside a frame like real hardware
+    pub fn set_cycle_timed_timers(&mut self, enabled: bool) {
+        self.cycle_timed_timers = enabled;
+    }
+
+    pub fn set_timer_rate_hz(&mut self, hz: u32) {
+        self.timer_rate_hz = hz;
+    }
+
+    pub fn timer_rate_hz(&self) -> u32 {
+        self.timer_rate_hz
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    // some interpreters stay silent for ST == 1 since it runs out within the frame,
+    // a threshold of 1 copies that, the default 0 beeps for any nonzero value
+    pub fn set_beep_threshold(&mut self, threshold: u8) {
+        self.beep_threshold = threshold;
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        self.sound_timer > self.beep_threshold
+    }
+
+    // for one shot sounds, called with the new sound timer value whenever FX18 starts a
+    // beep from silence, a beep that's extended while still playing doesn't call it again
+    pub fn set_on_beep_start(&mut self, hook: impl FnMut(u8) + Send + 'static) {
+        self.on_beep_start = Some(Box::new(hook));
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
@@ -70,49 +349,655 @@ impl Chip8 {
             pc: 0,
             i: 0,
             vx: [0; 16],
-            display: [0; 64 * 32],
+            display: [0; HI_WIDTH * HI_HEIGHT],
+            hires: false,
             draw_flag: false,
-            stack: [0; 16],
+            stack: vec![0; DEFAULT_STACK_SIZE],
             sp: 0,
             keypad: [false; 16],
             delay_timer: 0,
             sound_timer: 0,
+            entry_point: DEFAULT_ENTRY_POINT,
+            cycles: 0,
+            fps: 0,
+            show_stats: false,
+            cycles_per_tick: 10,
+            auto_idle_throttle: false,
+            idle: false,
+            timer_wait_loop: None,
+            quirks: Quirks::default(),
+            platform: None,
+            protect_font: true,
+            timer_rate_hz: 60,
+            timer_accum: 0,
+            cycle_accum: 0,
+            cycle_timed_timers: false,
+            timer_cycle_accum: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            keymap: KeyMap::default(),
+            strict: false,
+            break_on_unknown: false,
+            unknown_opcodes: 0,
+            unhandled: BTreeMap::new(),
+            beep_threshold: 0,
+            rng: SplitMix64::new(rand::random()),
+            rng_algorithm: RngAlgorithm::Modern,
+            vip_rng: VipRandom::new(rand::random()),
+            crt_effect: false,
+            scanline_intensity: 40,
+            crt_bloom: false,
+            frame_blend: false,
+            prev_display: [0; HI_WIDTH * HI_HEIGHT],
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            disabled_opcodes: HashSet::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            clock: Some(Box::new(InstantClock::new())),
+            #[cfg(target_arch = "wasm32")]
+            clock: None,
+            last_update_ms: None,
+            collision_since_last_check: false,
+            zero_opcode_run: 0,
+            rpl_flags: [0; 16],
+            rom_hash: None,
+            rom_len: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            persistence_dir: None,
+            on_beep_start: None,
+            stack_overflowed: false,
+            stack_underflowed: false,
+            use_instruction_cache: false,
+            instruction_cache: None,
+            draw_mode: DrawMode::Xor,
+            fx0a_selection: Fx0aMode::LowestIndex,
+            frame_pacing: false,
+            sent_pixels: None,
+            last_draw_bounds: None,
+            debug_extensions: false,
+            rng_log: None,
+            rng_replay: VecDeque::new(),
+            rng_frozen: false,
+            key_presses: 0,
+            key_pressed_at: [0; 16],
+            queued_input: VecDeque::new(),
+            input_frame: 0,
         }
     }
 
+    // warm boot: cpu state, screen and timers go back to power on, ram is left alone so a
+    // program already in memory restarts from the entry point (the font is restored too)
+    pub fn reset(&mut self) {
+        self.reset_machine();
+        self.pc = self.entry_point;
+    }
+
+    // cold boot: like switching the machine off and on, all ram is zeroed except the font
+    // and nothing is loaded, call load_rom afterwards. settings like quirks are kept, queued
+    // rng bytes, a frozen rng and queued input are dropped
+    pub fn cold_boot(&mut self) {
+        self.ram.fill(0);
+        self.reset_machine();
+        self.pc = 0;
+        self.rng_replay.clear();
+        self.rng_frozen = false;
+        self.queued_input.clear();
+        self.rom_len = 0;
+        self.invalidate_instruction_cache();
+    }
+
+    fn reset_machine(&mut self) {
+        self.ram[FONT_START_ADDR..(FONT_START_ADDR + FONT_SET.len())].copy_from_slice(&FONT_SET);
+        self.i = 0;
+        self.vx = [0; 16];
+        self.display = [0; HI_WIDTH * HI_HEIGHT];
+        self.prev_display = [0; HI_WIDTH * HI_HEIGHT];
+        self.hires = false;
+        self.draw_flag = true;
+        self.stack.fill(0);
+        self.sp = 0;
+        self.keypad = [false; 16];
+        self.key_pressed_at = [0; 16];
+        self.last_draw_bounds = None;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.timer_accum = 0;
+        self.cycle_accum = 0;
+        self.timer_cycle_accum = 0;
+        self.cycles = 0;
+        self.idle = false;
+        self.timer_wait_loop = None;
+        self.unknown_opcodes = 0;
+        self.unhandled.clear();
+        self.zero_opcode_run = 0;
+    }
+
     pub fn load_rom(&mut self, data: &[u8]) {
-        let start_addr: usize = 0x200;
+        let start_addr = self.entry_point as usize;
         self.pc = start_addr as u16;
         let max_len = self.ram.len() - start_addr;
         let copy_len = data.len().min(max_len);
         let end_addr = start_addr + copy_len;
         self.ram[start_addr..end_addr].copy_from_slice(&data[..copy_len]);
+        // the last instruction of an odd sized ROM would pick up a byte left by the one before
+        if copy_len % 2 == 1 && end_addr < self.ram.len() {
+            self.ram[end_addr] = 0;
+        }
+        self.rom_hash = Some(fnv1a(data));
+        self.rom_len = copy_len;
+        self.invalidate_instruction_cache();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.restore_flags();
+    }
+
+    // load_rom for data from outside, refuses a ROM without a single whole instruction
+    pub fn try_load_rom(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        if data.len() < 2 {
+            return Err(Chip8Error::RomTooSmall { len: data.len() });
+        }
+        self.load_rom(data);
+        Ok(())
+    }
+
+    // FNV-1a of the last ROM passed to load_rom
+    pub fn rom_hash(&self) -> Option<u64> {
+        self.rom_hash
+    }
+
+    pub fn rpl_flags(&self) -> [u8; 16] {
+        self.rpl_flags
+    }
+
+    pub fn set_entry_point(&mut self, addr: u16) {
+        self.entry_point = addr;
+    }
+
+    pub fn entry_point(&self) -> u16 {
+        self.entry_point
+    }
+
+    // make sure a program was actually loaded before running it
+    pub fn check_entry(&self) -> Result<(), Chip8Error> {
+        if self.pc == 0 {
+            return Err(Chip8Error::NoRomLoaded);
+        }
+        if self.pc < self.entry_point {
+            return Err(Chip8Error::EntryBelowMinimum {
+                pc: self.pc,
+                minimum: self.entry_point,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            fps: self.fps,
+            cycles: self.cycles,
+        }
+    }
+
+    pub fn set_fps(&mut self, fps: u32) {
+        self.fps = fps;
+    }
+
+    pub fn set_show_stats(&mut self, show: bool) {
+        self.show_stats = show;
+    }
+
+    pub fn show_stats(&self) -> bool {
+        self.show_stats
+    }
+
+    pub fn set_cycles_per_tick(&mut self, cycles: usize) {
+        self.cycles_per_tick = cycles;
+    }
+
+    pub fn cycles_per_tick(&self) -> usize {
+        self.cycles_per_tick
+    }
+
+    // turns on opcodes no real interpreter has, so test ROMs can set up the emulator
+    // themselves, for now only 01NN (cycles_per_tick = NN), which is otherwise a SYS
+    // into the interpreter area that the emulator ignores anyway, off by default
+    pub fn set_debug_extensions(&mut self, enabled: bool) {
+        self.debug_extensions = enabled;
+    }
+
+    // while the game waits on FX0A, a JP to itself or a short delay timer loop run_frame
+    // only runs enough instructions to notice the wait ending, until a key goes down
+    pub fn set_auto_idle_throttle(&mut self, enabled: bool) {
+        self.auto_idle_throttle = enabled;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    // same seed and same input gives the same CXNN results every run
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SplitMix64::new(seed);
+        self.vip_rng = VipRandom::new(seed);
+    }
+
+    pub fn set_rng_algorithm(&mut self, algorithm: RngAlgorithm) {
+        self.rng_algorithm = algorithm;
+    }
+
+    // records the random bytes CXNN uses from now on, turning it on starts an empty log
+    pub fn set_rng_logging(&mut self, enabled: bool) {
+        self.rng_log = enabled.then(Vec::new);
+    }
+
+    // the bytes before CXNN masked them, empty while logging is off
+    pub fn rng_log(&self) -> &[u8] {
+        self.rng_log.as_deref().unwrap_or_default()
+    }
+
+    // CXNN uses these bytes in order before going back to the rng, so a bug report's
+    // rng_log reproduces the run without knowing the seed
+    pub fn replay_rng(&mut self, log: &[u8]) {
+        self.rng_replay = log.iter().copied().collect();
+    }
+
+    // CXNN always sees FROZEN_RANDOM_BYTE until thaw_rng, for screenshots and thumbnails
+    // that have to come out the same, the seed and any replay carry on where they were
+    pub fn freeze_rng(&mut self) {
+        self.rng_frozen = true;
+    }
+
+    pub fn thaw_rng(&mut self) {
+        self.rng_frozen = false;
+    }
+
+    fn next_random_byte(&mut self) -> u8 {
+        let byte = if self.rng_frozen {
+            FROZEN_RANDOM_BYTE
+        } else {
+            match (self.rng_replay.pop_front(), self.rng_algorithm) {
+                (Some(byte), _) => byte,
+                (None, RngAlgorithm::Modern) => self.rng.next_u8(),
+                (None, RngAlgorithm::CosmacLfsr) => {
+                    self.vip_rng.next_u8(&self.ram[VIP_INTERPRETER_PAGE])
+                }
+            }
+        };
+        if let Some(log) = &mut self.rng_log {
+            log.push(byte);
+        }
+        byte
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // for debugging a crashing ROM, step reports an unknown opcode instead of moving on
+    // and run_frame/run_for/run_to stop right after it, strict mode still wins if both are on
+    pub fn set_break_on_unknown(&mut self, enabled: bool) {
+        self.break_on_unknown = enabled;
+    }
+
+    pub fn unknown_opcode_count(&self) -> u64 {
+        self.unknown_opcodes
+    }
+
+    // every distinct unknown opcode since the last reset with how many times it ran,
+    // ordered by opcode, worth attaching to a bug report for a misbehaving ROM
+    pub fn unhandled_report(&self) -> Vec<(u16, u64)> {
+        self.unhandled
+            .iter()
+            .map(|(&opcode, &count)| (opcode, count))
+            .collect()
+    }
+
+    // fetch and execute a single instruction
+    // fetch already moved pc past an unknown opcode so lenient mode always makes progress
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        let addr = self.pc;
+        let cached = self
+            .instruction_cache
+            .as_ref()
+            .and_then(|cache| cache.get(addr as usize));
+        let instruction = match cached {
+            Some(instruction) => {
+                self.pc = self.pc.wrapping_add(2) & self.pc_mask();
+                instruction
+            }
+            None => Instruction::decode(self.fetch()),
+        };
+        let opcode = instruction.opcode;
+        let unknown_before = self.unknown_opcodes;
+        self.execute(instruction);
+        // skips and BNNN can still step past the top of ram
+        self.pc &= self.pc_mask();
+        if self.cycle_timed_timers {
+            self.advance_timers_by_cycles(1);
+        }
+        if std::mem::take(&mut self.stack_overflowed) {
+            return Err(Chip8Error::StackOverflow {
+                addr,
+                depth: self.stack.len(),
+            });
+        }
+        if std::mem::take(&mut self.stack_underflowed) {
+            return Err(Chip8Error::StackUnderflow { addr });
+        }
+        if self.strict && self.unknown_opcodes != unknown_before {
+            return Err(Chip8Error::UnknownOpcode { opcode, addr });
+        }
+        if self.break_on_unknown && self.unknown_opcodes != unknown_before {
+            return Ok(StepOutcome::UnknownOpcode(opcode));
+        }
+
+        // 0x0000 decodes as a harmless SYS so a crashed program would otherwise spin quietly
+        if opcode == 0x0000 {
+            self.zero_opcode_run += 1;
+        } else {
+            self.zero_opcode_run = 0;
+        }
+        if self.strict && self.is_runaway() {
+            return Err(Chip8Error::ExecutingEmptyMemory { addr });
+        }
+        Ok(StepOutcome::Executed)
+    }
+
+    // how many nested calls fit before CALL fails, DEFAULT_STACK_SIZE unless changed
+    // shrinking it below the current depth drops the outer return addresses
+    pub fn set_stack_size(&mut self, size: usize) {
+        self.stack.resize(size, 0);
+        self.sp = self.sp.min(size as u16);
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack.len()
+    }
+
+    // how many subroutines deep the program is, 0 at the top level
+    pub fn call_depth(&self) -> usize {
+        self.sp as usize
+    }
+
+    // pc has been running through zeroed memory, the program most likely crashed
+    pub fn is_runaway(&self) -> bool {
+        self.zero_opcode_run >= RUNAWAY_ZERO_OPCODES
+    }
+
+    // runs one 60Hz frame worth of instructions then advances the timers by a frame
+    // returns how many instructions were executed
+    pub fn run_frame(&mut self) -> Result<usize, Chip8Error> {
+        let throttled = self.auto_idle_throttle && self.idle && !self.keypad.iter().any(|&k| k);
+        let budget = match self.timer_wait_loop {
+            Some((start, jump)) if throttled => ((jump - start) / 2 + 1) as usize,
+            _ if throttled => IDLE_CYCLES_PER_TICK,
+            _ => self.cycles_per_tick,
+        };
+
+        self.apply_queued_input();
+        self.prev_display = self.display;
+        let mut executed = 0;
+        while executed < budget {
+            let opcode_addr = self.pc;
+            let outcome = self.step()?;
+            executed += 1;
+            self.track_timer_wait_loop(opcode_addr);
+            // a game spinning on FX0A or `JP self` leaves pc where it started,
+            // a crashed one sliding through empty ram isn't going anywhere either
+            self.idle =
+                self.pc == opcode_addr || self.is_runaway() || self.timer_wait_loop.is_some();
+            if outcome != StepOutcome::Executed {
+                break;
+            }
+        }
+
+        if !self.cycle_timed_timers {
+            self.advance_timers(FRAME_DURATION);
+        }
+        Ok(executed)
+    }
+
+    // notices a short backward JP over nothing but DT reads and skips, forgets the loop
+    // again once pc leaves it, with cycle timed timers the loop needs every instruction
+    // to count the timer down so it isn't tracked at all
+    fn track_timer_wait_loop(&mut self, opcode_addr: u16) {
+        if let Some((start, jump)) = self.timer_wait_loop
+            && !(start..=jump).contains(&self.pc)
+        {
+            self.timer_wait_loop = None;
+        }
+        let opcode = self.peek_opcode(opcode_addr);
+        if self.cycle_timed_timers || opcode >> 12 != 0x1 {
+            return;
+        }
+        let start = opcode & 0x0FFF;
+        if start >= opcode_addr || opcode_addr - start > 2 * (MAX_TIMER_WAIT_INSTRUCTIONS - 1) {
+            return;
+        }
+        let body: Vec<u16> = (start..opcode_addr)
+            .step_by(2)
+            .map(|addr| self.peek_opcode(addr))
+            .collect();
+        let reads_dt = body.iter().any(|&op| op & 0xF0FF == 0xF007);
+        let only_waits = body
+            .iter()
+            .all(|&op| op & 0xF0FF == 0xF007 || matches!(op >> 12, 0x3 | 0x4));
+        if reads_dt && only_waits {
+            self.timer_wait_loop = Some((start, opcode_addr));
+        }
+    }
+
+    // run_frame for a "next frame" debugger button, also says what the frame did
+    // draw_flag is left set if it was before so a front-end still sees pending redraws
+    pub fn step_frame(&mut self) -> Result<FrameOutcome, Chip8Error> {
+        let pending_redraw = std::mem::take(&mut self.draw_flag);
+        let result = self.run_frame();
+        let drew = self.draw_flag;
+        self.draw_flag |= pending_redraw;
+        Ok(FrameOutcome {
+            cycles: result?,
+            drew,
+            beeping: self.is_beeping(),
+        })
+    }
+
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.quirks = platform.quirks();
+        self.platform = Some(platform);
+    }
+
+    pub fn platform(&self) -> Option<Platform> {
+        self.platform
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // returns false if there is no quirk with that name
+    pub fn set_quirk(&mut self, name: &str, enabled: bool) -> bool {
+        self.quirks.set(name, enabled)
+    }
+
+    // human readable summary of the active compatibility mode, one setting per line
+    pub fn quirk_report(&self) -> String {
+        let mut report = match self.platform {
+            Some(platform) if platform.quirks() == self.quirks => {
+                format!("platform: {}\n", platform)
+            }
+            Some(platform) => format!("platform: {} (modified)\n", platform),
+            None => String::from("platform: default\n"),
+        };
+        for name in Quirks::NAMES {
+            let on = self.quirks.get(name).unwrap_or(false);
+            report.push_str(&format!("{}: {}\n", name, if on { "on" } else { "off" }));
+        }
+        report
+    }
+
+    // where the built in font lives and how many bytes each glyph takes
+    pub fn font_info(&self) -> (u16, usize) {
+        (FONT_START_ADDR as u16, FONT_GLYPH_SIZE)
+    }
+
+    // presses and releases a chip-8 key 0-F, keys past F are ignored
+    // writing `keypad` directly works too but FX0A can't tell which key went down last
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let idx = key as usize;
+        if idx >= self.keypad.len() {
+            return;
+        }
+        if pressed && !self.keypad[idx] {
+            self.key_presses += 1;
+            self.key_pressed_at[idx] = self.key_presses;
+        }
+        self.keypad[idx] = pressed;
+    }
+
+    pub fn set_fx0a_selection(&mut self, mode: Fx0aMode) {
+        self.fx0a_selection = mode;
+    }
+
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self.draw_mode = mode;
+    }
+
+    pub fn draw_mode(&self) -> DrawMode {
+        self.draw_mode
+    }
+
+    pub fn set_protect_font(&mut self, protect: bool) {
+        self.protect_font = protect;
+    }
+
+    // every write an instruction makes to ram goes through here
+    fn write_ram(&mut self, addr: usize, value: u8) {
+        let addr = addr & ADDR_MASK;
+        let font_region = FONT_START_ADDR..(FONT_START_ADDR + FONT_SET.len());
+        if self.protect_font && font_region.contains(&addr) {
+            return;
+        }
+        self.ram[addr] = value;
+        if let Some(cache) = &mut self.instruction_cache {
+            cache.mark_dirty(addr);
+        }
+    }
+
+    // same as fetch but leaves pc alone, addresses past the end of ram wrap around
+    pub fn peek_opcode(&self, addr: u16) -> u16 {
+        read_opcode(&self.ram, addr as usize)
+    }
+
+    // instructions per second, cycles_per_tick at 60 frames a second
+    pub fn clock_hz(&self) -> u32 {
+        self.cycles_per_tick as u32 * 60
+    }
+
+    // runs as many instructions and timer ticks as fit in `elapsed` of real time, so the
+    // speed doesn't depend on how often the caller shows up (120Hz and 144Hz monitors)
+    // returns how many instructions were executed
+    pub fn run_for(&mut self, elapsed: Duration) -> Result<usize, Chip8Error> {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        let elapsed = elapsed.min(MAX_RUN_FOR);
+        self.cycle_accum += elapsed.as_nanos() * self.clock_hz() as u128;
+        let cycles = (self.cycle_accum / NANOS_PER_SEC) as usize;
+        self.cycle_accum %= NANOS_PER_SEC;
+
+        self.prev_display = self.display;
+        let mut executed = 0;
+        while executed < cycles {
+            let outcome = self.step()?;
+            executed += 1;
+            if outcome != StepOutcome::Executed {
+                break;
+            }
+        }
+        if !self.cycle_timed_timers {
+            self.advance_timers(elapsed);
+        }
+        Ok(executed)
+    }
+
+    // steps until the cycle counter reaches target_cycles, for golden tests that pin the
+    // state at an exact instruction count, does nothing if the counter is already past it
+    // timers are left alone so the result only depends on the instructions executed
+    pub fn run_to(&mut self, target_cycles: u64) -> Result<(), Chip8Error> {
+        while self.cycles < target_cycles {
+            if self.step()? != StepOutcome::Executed {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock + Send>) {
+        self.clock = Some(clock);
+        self.last_update_ms = None;
+    }
+
+    // runs whatever real time passed on the clock since the last call, see run_for
+    // the first call only starts the clock
+    pub fn update(&mut self) -> Result<usize, Chip8Error> {
+        let Some(clock) = &self.clock else {
+            return Ok(0);
+        };
+        let now = clock.now_ms();
+        let elapsed_ms = match self.last_update_ms {
+            Some(last) => (now - last).max(0.0),
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now);
+        self.run_for(Duration::from_secs_f64(elapsed_ms / 1000.0))
     }
 
     pub fn fetch(&mut self) -> u16 {
-        let high_byte = self.ram[self.pc as usize];
-        let low_byte = self.ram[self.pc as usize + 1];
-        self.pc += 2;
-        //shift high bytes to left by 8 pos so first cast to 16
-        let opcode = ((high_byte as u16) << 8) | (low_byte as u16);
+        let opcode = read_opcode(&self.ram, self.pc as usize);
+        self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         opcode
     }
 
+    // pc only has as many bits as ram has addresses, 12 for the 4KB ram, so running off
+    // the top carries on at 0 like the hardware
+    fn pc_mask(&self) -> u16 {
+        (self.ram.len() - 1) as u16
+    }
+
     pub fn decode_execute(&mut self, opcode: u16) {
-        let primary = (opcode & 0xF000) >> 12; // 0x0FFF is mask to just extract pos 12-15
-        let x = ((opcode & 0x0F00) >> 8) as usize;
-        let y = ((opcode & 0x00F0) >> 4) as usize;
-        let n = (opcode & 0x000F) as u8;
+        self.execute(Instruction::decode(opcode));
+    }
 
-        // Also common helpers:
-        let nn = (opcode & 0x00FF) as u8;
-        let nnn = opcode & 0x0FFF;
+    fn execute(&mut self, instruction: Instruction) {
+        let Instruction {
+            opcode,
+            primary,
+            x,
+            y,
+            n,
+            nn,
+            nnn,
+        } = instruction;
+
+        self.cycles += 1;
+
+        if !self.disabled_opcodes.is_empty()
+            && opcode_group(opcode).is_some_and(|group| self.disabled_opcodes.contains(&group))
+        {
+            self.unknown_opcode(opcode);
+            return;
+        }
 
         let nibbles = (primary, x, y, n);
         match nibbles {
             // --- 0 Series ---
             (0x0, 0x0, 0xE, 0x0) => self.op_00e0(), //CLS
             (0x0, 0x0, 0xE, 0xE) => self.op_00ee(), // RET
+            // 00CN needs the literal C nibble, any other 00XN is still a SYS
+            (0x0, 0x0, 0xC, n) if self.quirks.superchip_opcodes => self.op_00cn(n), // SCD n
+            (0x0, 0x0, 0xF, 0xE) if self.quirks.superchip_opcodes => self.op_00fe(), // LOW
+            (0x0, 0x0, 0xF, 0xF) if self.quirks.superchip_opcodes => self.op_00ff(), // HIGH
+            (0x0, 0x1, _, _) if self.debug_extensions => self.op_01nn(nn), // SPEED nn (debug)
             (0x0, _, _, _) => self.op_0nnn(nnn),    // SYS addr (Usually ignored)
 
             // --- Standard Logic/Flow ---
@@ -138,7 +1023,7 @@ impl Chip8 {
             // --- Offset/Random/Display ---
             (0x9, _, _, 0x0) => self.op_9xy0(x, y), // SNE Vx, Vy
             (0xA, _, _, _) => self.op_annn(nnn),    // LD I, addr
-            (0xB, _, _, _) => self.op_bnnn(nnn),    // JP V0, addr
+            (0xB, _, _, _) => self.op_bnnn(x, nnn), // JP V0, addr
             (0xC, _, _, _) => self.op_cxnn(x, nn),  // RND Vx, byte
             (0xD, _, _, n) => self.op_dxyn(x, y, n), // DRW Vx, Vy, nibble
 
@@ -156,22 +1041,127 @@ impl Chip8 {
             (0xF, _, 0x3, 0x3) => self.op_fx33(x), // LD B, Vx
             (0xF, _, 0x5, 0x5) => self.op_fx55(x), // LD [I], Vx
             (0xF, _, 0x6, 0x5) => self.op_fx65(x), // LD Vx, [I]
+            (0xF, _, 0x7, 0x5) if self.quirks.superchip_opcodes => self.op_fx75(x), // LD R, Vx
+            (0xF, _, 0x8, 0x5) if self.quirks.superchip_opcodes => self.op_fx85(x), // LD Vx, R
+
+            _ => self.unknown_opcode(opcode),
+        }
+    }
+
+    fn unknown_opcode(&mut self, opcode: u16) {
+        self.unknown_opcodes += 1;
+        let count = self.unhandled.entry(opcode).or_insert(0);
+        *count += 1;
+        // only the first time, a ROM stuck on one would otherwise flood the console
+        if *count == 1 {
+            println!("Unknown Opcode: {:#06x}", opcode)
+        }
+    }
+
+    // (width, height) of the current resolution
+    pub fn dimensions(&self) -> (usize, usize) {
+        if self.hires {
+            (HI_WIDTH, HI_HEIGHT)
+        } else {
+            (WIDTH, HEIGHT)
+        }
+    }
+
+    // the pixels of the current resolution, row by row
+    pub fn active_display(&self) -> &[u8] {
+        let (width, height) = self.dimensions();
+        &self.display[..width * height]
+    }
+
+    // copies active_display into a buffer the caller keeps around, so drawing every frame
+    // doesn't allocate, the buffer has to be exactly width * height long
+    pub fn frame_into(&self, out: &mut [u8]) -> Result<(), Chip8Error> {
+        let frame = self.active_display();
+        if out.len() != frame.len() {
+            return Err(Chip8Error::DisplaySizeMismatch {
+                expected: frame.len(),
+                actual: out.len(),
+            });
+        }
+        out.copy_from_slice(frame);
+        Ok(())
+    }
+
+    pub fn display_bools(&self) -> Vec<bool> {
+        self.active_display().iter().map(|&p| p == 1).collect()
+    }
 
-            _ => println!("Unknown Opcode: {:#06x}", opcode),
+    // replaces the screen contents, one byte (0 or 1) per pixel of the current resolution
+    pub fn set_display(&mut self, buffer: &[u8]) -> Result<(), Chip8Error> {
+        let (width, height) = self.dimensions();
+        if buffer.len() != width * height {
+            return Err(Chip8Error::DisplaySizeMismatch {
+                expected: width * height,
+                actual: buffer.len(),
+            });
         }
+        self.display[..buffer.len()].copy_from_slice(buffer);
+        self.draw_flag = true;
+        Ok(())
+    }
+
+    // true if any sprite collided since the previous call, unlike VF this survives later draws
+    pub fn check_collision(&mut self) -> bool {
+        std::mem::take(&mut self.collision_since_last_check)
+    }
+
+    // pressed state arranged like the real keypad (see KEYPAD_LAYOUT) for drawing it in a UI
+    pub fn keypad_grid(&self) -> [[bool; 4]; 4] {
+        KEYPAD_LAYOUT.map(|row| row.map(|key| self.keypad[key as usize]))
+    }
+
+    // FNV-1a of the active display, cheap to compare against a recorded golden value
+    pub fn display_hash(&self) -> u64 {
+        fnv1a(self.active_display())
+    }
+
+    // where the most recent DXYN put its sprite as (x, y, width, height), the position is
+    // after wrapping onto the screen and the size is the sprite's, even if it was clipped
+    pub fn last_draw_bounds(&self) -> Option<(u8, u8, u8, u8)> {
+        self.last_draw_bounds
+    }
+
+    // true when the screen changed since the front-end last called mark_drawn
+    pub fn needs_redraw(&self) -> bool {
+        self.draw_flag
+    }
+
+    pub fn mark_drawn(&mut self) {
+        self.draw_flag = false;
+    }
+
+    pub fn is_display_blank(&self) -> bool {
+        self.display.iter().all(|&p| p == 0)
+    }
+
+    // false for coordinates outside the screen
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let (width, height) = self.dimensions();
+        x < width && y < height && self.display[x + y * width] == 1
     }
+
     pub fn debug_render_console(&self) {
         // Clear console (ANSI escape code)
         print!("{}[2J", 27 as char);
+        // Use a block character for 'on' and a space for 'off'
+        print!("{}", self.display_to_string('█', ' '));
+    }
 
-        for y in 0..32 {
-            for x in 0..64 {
-                let pixel = self.display[x + y * 64];
-                // Use a block character for 'on' and a space for 'off'
-                print!("{}", if pixel == 1 { "█" } else { " " });
-            }
-            println!();
+    // one line per row of the current resolution, each ending in a newline
+    // handy for snapshot tests of what a ROM drew
+    pub fn display_to_string(&self, on: char, off: char) -> String {
+        let (width, height) = self.dimensions();
+        let mut out = String::with_capacity((width + 1) * height);
+        for row in self.active_display().chunks_exact(width) {
+            out.extend(row.iter().map(|&pixel| if pixel == 1 { on } else { off }));
+            out.push('\n');
         }
+        out
     }
     // --- 0 Series: System and Control ---
     fn op_00e0(&mut self) {
@@ -182,10 +1172,47 @@ impl Chip8 {
 
     fn op_00ee(&mut self) {
         // RET: Return from a subroutine
+        // with nothing to return to pc carries on and step reports the underflow
+        if self.sp == 0 {
+            self.stack_underflowed = true;
+            return;
+        }
         self.sp -= 1;
         self.pc = self.stack[self.sp as usize];
     }
 
+    fn op_00cn(&mut self, n: u8) {
+        // SCD n: Scroll the display down n rows, the rows that come in at the top are blank
+        if n == 0 {
+            return;
+        }
+        let (width, height) = self.dimensions();
+        let shift = (n as usize).min(height) * width;
+        let area = &mut self.display[..width * height];
+        area.copy_within(..area.len() - shift, shift);
+        area[..shift].fill(0);
+        self.draw_flag = true;
+    }
+
+    fn op_00fe(&mut self) {
+        // LOW: Switch to 64x32, the old picture doesn't fit the new layout so clear it
+        self.hires = false;
+        self.op_00e0();
+    }
+
+    fn op_00ff(&mut self) {
+        // HIGH: Switch to 128x64 (SUPER-CHIP)
+        self.hires = true;
+        self.op_00e0();
+    }
+
+    fn op_01nn(&mut self, nn: u8) {
+        // SPEED nn: Set cycles_per_tick = nn, a speed of 0 would stall the ROM so it's ignored
+        if nn > 0 {
+            self.cycles_per_tick = nn as usize;
+        }
+    }
+
     fn op_0nnn(&mut self, _addr: u16) {
         // SYS addr: Execute machine language routine (Usually ignored)
     }
@@ -198,6 +1225,11 @@ impl Chip8 {
 
     fn op_2nnn(&mut self, addr: u16) {
         // CALL addr: Call subroutine at NNN
+        // with no room left the call is dropped and step reports the overflow
+        if self.sp as usize >= self.stack.len() {
+            self.stack_overflowed = true;
+            return;
+        }
         self.stack[self.sp as usize] = self.pc; //store current address
         self.sp += 1;
         self.pc = addr;
@@ -206,21 +1238,21 @@ impl Chip8 {
     fn op_3xnn(&mut self, x: usize, nn: u8) {
         // SE Vx, byte: Skip next instruction if Vx == NN
         if self.vx[x] == nn {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
     fn op_4xnn(&mut self, x: usize, nn: u8) {
         // SNE Vx, byte: Skip next instruction if Vx != NN
         if self.vx[x] != nn {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
     fn op_5xy0(&mut self, x: usize, y: usize) {
         // SE Vx, Vy: Skip next instruction if Vx == Vy
         if self.vx[x] == self.vx[y] {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
@@ -231,7 +1263,10 @@ impl Chip8 {
 
     fn op_7xnn(&mut self, x: usize, nn: u8) {
         // ADD Vx, byte: Set Vx = Vx + NN (No Carry Flag)
+        // no interpreter sets a carry here, unlike 8XY4, so VF only changes for 7FNN
+        let vf = self.vx[0xF];
         self.vx[x] = self.vx[x].wrapping_add(nn);
+        debug_assert!(x == 0xF || self.vx[0xF] == vf, "7XNN changed VF");
     }
 
     // --- 8 Series: Arithmetic and Bitwise ---
@@ -243,16 +1278,25 @@ impl Chip8 {
     fn op_8xy1(&mut self, x: usize, y: usize) {
         // OR Vx, Vy: Set Vx = Vx OR Vy
         self.vx[x] |= self.vx[y];
+        if self.quirks.vf_reset {
+            self.vx[0xF] = 0;
+        }
     }
 
     fn op_8xy2(&mut self, x: usize, y: usize) {
         // AND Vx, Vy: Set Vx = Vx AND Vy
         self.vx[x] &= self.vx[y];
+        if self.quirks.vf_reset {
+            self.vx[0xF] = 0;
+        }
     }
 
     fn op_8xy3(&mut self, x: usize, y: usize) {
         // XOR Vx, Vy: Set Vx = Vx XOR Vy
         self.vx[x] ^= self.vx[y];
+        if self.quirks.vf_reset {
+            self.vx[0xF] = 0;
+        }
     }
 
     fn op_8xy4(&mut self, x: usize, y: usize) {
@@ -273,31 +1317,43 @@ impl Chip8 {
 
     fn op_8xy5(&mut self, x: usize, y: usize) {
         // SUB Vx, Vy: Set Vx = Vx - Vy, set VF = NOT borrow
-        self.vx[0xF] = if self.vx[x] >= self.vx[y] { 1 } else { 0 };
+        // flag is written last so it wins when x is VF
+        let not_borrow = if self.vx[x] >= self.vx[y] { 1 } else { 0 };
         self.vx[x] = self.vx[x].wrapping_sub(self.vx[y]);
+        self.vx[0xF] = not_borrow;
     }
 
-    fn op_8xy6(&mut self, x: usize, _y: usize) {
+    fn op_8xy6(&mut self, x: usize, y: usize) {
         // SHR: Set VF to the least significant bit, then shift Vx right by 1
-        self.vx[0xF] = self.vx[x] & 0x1;
+        if self.quirks.shift_uses_vy {
+            self.vx[x] = self.vx[y];
+        }
+        // flag is written last so it wins when x is VF
+        let shifted_out = self.vx[x] & 0x1;
         self.vx[x] >>= 1;
+        self.vx[0xF] = shifted_out;
     }
 
     fn op_8xy7(&mut self, x: usize, y: usize) {
         // SUBN Vx, Vy: Set Vx = Vy - Vx, set VF = NOT borrow
-        self.vx[0xF] = if self.vx[y] >= self.vx[x] { 1 } else { 0 };
+        let not_borrow = if self.vx[y] >= self.vx[x] { 1 } else { 0 };
         self.vx[x] = self.vx[y].wrapping_sub(self.vx[x]);
+        self.vx[0xF] = not_borrow;
     }
-    fn op_8xye(&mut self, x: usize, _y: usize) {
+    fn op_8xye(&mut self, x: usize, y: usize) {
         // SHL: Set VF to the most significant bit, then shift Vx left by 1
-        self.vx[0xF] = (self.vx[x] & 0x80) >> 7;
+        if self.quirks.shift_uses_vy {
+            self.vx[x] = self.vx[y];
+        }
+        let shifted_out = (self.vx[x] & 0x80) >> 7;
         self.vx[x] <<= 1;
+        self.vx[0xF] = shifted_out;
     }
     // --- 9 to D Series: Offsets, Random, and Graphics ---
     fn op_9xy0(&mut self, x: usize, y: usize) {
         // SNE Vx, Vy: Skip next instruction if Vx != Vy
         if self.vx[x] != self.vx[y] {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
@@ -306,47 +1362,75 @@ impl Chip8 {
         self.i = addr;
     }
 
-    fn op_bnnn(&mut self, addr: u16) {
+    fn op_bnnn(&mut self, x: usize, addr: u16) {
         // JP V0, addr: Jump to location NNN + V0
-        self.pc = addr + self.vx[0] as u16;
+        // CHIP-48 read this as BXNN and used Vx instead
+        let offset_reg = if self.quirks.jump_uses_vx { x } else { 0 };
+        self.pc = addr + self.vx[offset_reg] as u16;
     }
 
     fn op_cxnn(&mut self, x: usize, nn: u8) {
         // RND Vx, byte: Set Vx = random byte AND NN
-        let mut rng = rand::rng();
-        let random_byte: u8 = rng.random();
+        let random_byte = self.next_random_byte();
         self.vx[x] = random_byte & nn;
     }
 
     fn op_dxyn(&mut self, x_idx: usize, y_idx: usize, height: u8) {
-        let x_coord = (self.vx[x_idx] % 64) as usize;
-        let y_coord = (self.vx[y_idx] % 32) as usize;
-
-        let height = height as usize;
-        self.vx[0xF] = 0; // Reset collision flag
+        let (screen_w, screen_h) = self.dimensions();
+        let x_coord = self.vx[x_idx] as usize % screen_w;
+        let y_coord = self.vx[y_idx] as usize % screen_h;
+
+        // SUPER-CHIP DXY0 draws a 16x16 sprite stored as two bytes per row
+        let (sprite_w, height) = if height == 0 && self.quirks.superchip_opcodes {
+            (16, 16)
+        } else {
+            (8, height as usize)
+        };
+        let bytes_per_row = sprite_w / 8;
+        self.last_draw_bounds = Some((x_coord as u8, y_coord as u8, sprite_w as u8, height as u8));
+        if !self.quirks.vf_accumulate_collisions {
+            self.vx[0xF] = 0; // Reset collision flag
+        }
 
         for row in 0..height {
+            if self.quirks.clip_sprites && y_coord + row >= screen_h {
+                break;
+            }
             // Wrap the Y coordinate for the current row
-            let current_y = (y_coord + row) % 32;
-            let sprite_byte = self.ram[self.i as usize + row];
+            let current_y = (y_coord + row) % screen_h;
+            // sprite data running past the top of ram wraps back to 0 like any other read
+            let row_addr = self.i as usize + row * bytes_per_row;
+            let sprite_bits = if sprite_w == 16 {
+                ((self.ram[row_addr & ADDR_MASK] as u16) << 8)
+                    | self.ram[(row_addr + 1) & ADDR_MASK] as u16
+            } else {
+                (self.ram[row_addr & ADDR_MASK] as u16) << 8
+            };
 
-            for col in 0..8 {
+            for col in 0..sprite_w {
+                if self.quirks.clip_sprites && x_coord + col >= screen_w {
+                    break;
+                }
                 // Wrap the X coordinate for the current column
-                let current_x = (x_coord + col) % 64;
+                let current_x = (x_coord + col) % screen_w;
 
-                let mask = 0x80 >> col;
+                let mask = 0x8000 >> col;
 
                 //check if pixel in sprite is on
-                if (sprite_byte & mask) != 0 {
-                    let screen_idx = current_x + (current_y * 64);
+                if (sprite_bits & mask) != 0 {
+                    let screen_idx = current_x + (current_y * screen_w);
 
                     // Collision detection: if the screen pixel is already 1
                     if self.display[screen_idx] == 1 {
                         self.vx[0xF] = 1;
+                        self.collision_since_last_check = true;
                     }
 
-                    // XOR the pixel onto the screen
-                    self.display[screen_idx] ^= 1;
+                    match self.draw_mode {
+                        // XOR the pixel onto the screen
+                        DrawMode::Xor => self.display[screen_idx] ^= 1,
+                        DrawMode::Or => self.display[screen_idx] = 1,
+                    }
                 }
             }
         }
@@ -355,15 +1439,16 @@ impl Chip8 {
     // --- E Series: Input ---
     fn op_ex9e(&mut self, x: usize) {
         // SKP Vx: Skip next instruction if key with the value of Vx is pressed
-        if self.keypad[self.vx[x] as usize] {
-            self.pc += 2;
+        // only the low nibble selects a key, there are just 16 of them
+        if self.keypad[(self.vx[x] & 0x0F) as usize] {
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
     fn op_exa1(&mut self, x: usize) {
         // SKNP Vx: Skip next instruction if key with the value of Vx is not pressed
-        if !self.keypad[self.vx[x] as usize] {
-            self.pc += 2;
+        if !self.keypad[(self.vx[x] & 0x0F) as usize] {
+            self.pc = self.pc.wrapping_add(2) & self.pc_mask();
         }
     }
 
@@ -375,16 +1460,15 @@ impl Chip8 {
 
     fn op_fx0a(&mut self, x: usize) {
         // LD Vx, K: Wait for a key press, store the value of the key in Vx
-        let mut key_pressed = false;
-        for i in 1..self.keypad.len() {
-            if self.keypad[i] {
-                self.vx[x] = i as u8;
-                key_pressed = true;
-                break;
-            }
-        }
-        if !key_pressed {
-            self.pc -= 2; //this causes this instruction to play again and again effectively waiting for key press on this instruction
+        let held = (0..self.keypad.len()).filter(|&key| self.keypad[key]);
+        let key = match self.fx0a_selection {
+            Fx0aMode::LowestIndex => held.min(),
+            Fx0aMode::MostRecent => held.max_by_key(|&key| self.key_pressed_at[key]),
+        };
+        if let Some(key) = key {
+            self.vx[x] = key as u8;
+        } else {
+            self.pc = self.pc.wrapping_sub(2) & self.pc_mask(); //this causes this instruction to play again and again effectively waiting for key press on this instruction
         }
     }
 
@@ -395,18 +1479,22 @@ impl Chip8 {
 
     fn op_fx18(&mut self, x: usize) {
         // LD ST, Vx: Set sound timer = Vx
+        let starting = self.sound_timer == 0 && self.vx[x] != 0;
         self.sound_timer = self.vx[x];
+        if starting && let Some(hook) = &mut self.on_beep_start {
+            hook(self.sound_timer);
+        }
     }
 
     fn op_fx1e(&mut self, x: usize) {
         // ADD I, Vx: Set I = I + Vx
-        self.i = self.i + self.vx[x] as u16;
+        self.i += self.vx[x] as u16;
     }
 
     fn op_fx29(&mut self, x: usize) {
         // LD F, Vx: Set I = location of sprite for digit Vx
         let character = self.vx[x] as u16; //we have character loaded into memory FONT SET so vx contains the number that is to be printed
-        self.i = FONT_START_ADDR as u16 + (character * 5); //since every character is 5 bytes long
+        self.i = FONT_START_ADDR as u16 + (character * FONT_GLYPH_SIZE as u16); //since every character is 5 bytes long
     }
 
     fn op_fx33(&mut self, x: usize) {
@@ -415,23 +1503,78 @@ impl Chip8 {
         //since we cant extract out individual bits as we require division and chip8 has no division
         let value = self.vx[x];
         //get individual digit
-        self.ram[self.i as usize] = value / 100; // first digit
-        self.ram[self.i as usize + 1] = (value / 10) % 10; // second digit
-        self.ram[self.i as usize + 2] = value % 10; // last digit
+        let addr = self.i as usize;
+        self.write_ram(addr, value / 100); // first digit
+        self.write_ram(addr + 1, (value / 10) % 10); // second digit
+        self.write_ram(addr + 2, value % 10); // last digit
     }
 
     fn op_fx55(&mut self, x: usize) {
         // LD [I], Vx: Store registers V0 through Vx in memory starting at location I
         for i in 0..=x {
-            self.ram[self.i as usize + i] = self.vx[i];
+            self.write_ram(self.i as usize + i, self.vx[i]);
+        }
+        if self.quirks.load_store_increments_i {
+            self.i = (self.i + x as u16 + 1) & ADDR_MASK as u16;
         }
     }
 
     fn op_fx65(&mut self, x: usize) {
         // LD Vx, [I]: Read registers V0 through Vx from memory starting at location I
         for i in 0..=x {
-            self.vx[i] = self.ram[self.i as usize + i];
+            self.vx[i] = self.ram[(self.i as usize + i) & ADDR_MASK];
         }
+        if self.quirks.load_store_increments_i {
+            self.i = (self.i + x as u16 + 1) & ADDR_MASK as u16;
+        }
+    }
+
+    fn op_fx75(&mut self, x: usize) {
+        // LD R, Vx: Store V0 through Vx in the flag registers (SUPER-CHIP)
+        self.rpl_flags[..=x].copy_from_slice(&self.vx[..=x]);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.persist_flags();
+    }
+
+    fn op_fx85(&mut self, x: usize) {
+        // LD Vx, R: Read V0 through Vx from the flag registers (SUPER-CHIP)
+        self.vx[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+    }
+}
+
+// the big endian opcode at addr, both bytes wrap around past the end of `ram` so an opcode
+// straddling the top of memory takes its low byte from the start, empty ram reads as 0
+pub fn read_opcode(ram: &[u8], addr: usize) -> u16 {
+    if ram.is_empty() {
+        return 0;
+    }
+    let high_byte = ram[addr % ram.len()];
+    let low_byte = ram[(addr + 1) % ram.len()];
+    //shift high bytes to left by 8 pos so first cast to 16
+    ((high_byte as u16) << 8) | (low_byte as u16)
+}
+
+// 64 bit FNV-1a, small and stable across builds which is all ROM ids need
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+// minifb only supports power of two window scales, anything else is rejected
+#[cfg(not(target_arch = "wasm32"))]
+pub fn window_scale(factor: u32) -> Option<Scale> {
+    match factor {
+        1 => Some(Scale::X1),
+        2 => Some(Scale::X2),
+        4 => Some(Scale::X4),
+        8 => Some(Scale::X8),
+        16 => Some(Scale::X16),
+        32 => Some(Scale::X32),
+        _ => None,
     }
 }
 
@@ -439,64 +1582,74 @@ impl Chip8 {
 impl Chip8 {
     pub fn update_keypad(&mut self, window: &Window) {
         // update our keypad buffer position based on key press
-        self.keypad[0x1] = window.is_key_down(Key::W); //player 1 up
-        self.keypad[0x2] = window.is_key_down(Key::Key2);
-        self.keypad[0x3] = window.is_key_down(Key::Key3);
-        self.keypad[0xC] = window.is_key_down(Key::K); // player 2 up
-
-        self.keypad[0x4] = window.is_key_down(Key::Q); //player 1 down
-        self.keypad[0x5] = window.is_key_down(Key::W);
-        self.keypad[0x6] = window.is_key_down(Key::E);
-        self.keypad[0xD] = window.is_key_down(Key::J); //player 2 down
-
-        self.keypad[0x7] = window.is_key_down(Key::A);
-        self.keypad[0x8] = window.is_key_down(Key::S);
-        self.keypad[0x9] = window.is_key_down(Key::D);
-        self.keypad[0xE] = window.is_key_down(Key::F);
-
-        self.keypad[0xA] = window.is_key_down(Key::Z);
-        self.keypad[0x0] = window.is_key_down(Key::X);
-        self.keypad[0xB] = window.is_key_down(Key::C);
-        self.keypad[0xF] = window.is_key_down(Key::V);
-    }
-
-    pub fn run(&mut self, window: &mut Window, sound: &mut Sink) {
-        assert!(self.pc >= 0x200);
-
-        // Limit the window to 60 FPS for the timers
-        window.set_target_fps(60);
-
-        while window.is_open() && !window.is_key_down(Key::Escape) {
-            // 1. Update Keypad state
-            self.update_keypad(&window);
-
-            // 2. Run multiple CPU cycles per frame
-            // (At 60 FPS, 10 cycles per frame = 600Hz)
-            for _ in 0..10 {
-                let opcode = self.fetch();
-                self.decode_execute(opcode);
-            }
+        let keys = self.keymap.keys;
+        for (chip8_key, key) in keys.into_iter().enumerate() {
+            self.set_key(chip8_key as u8, window.is_key_down(key));
+        }
+    }
 
-            // 3. Update Timers (Once per frame)
-            self.tick_timers();
-            if self.sound_timer > 0 {
-                sound.play();
-            } else {
-                sound.pause();
-            }
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keymap = keymap;
+    }
 
-            // 4. Update Window Buffer
-            // minifb expects a Vec<u32> where each u32 is 0x00RRGGBB
-            if self.draw_flag {
-                let buffer: Vec<u32> = self
-                    .display
-                    .iter()
-                    .map(|&p| if p == 1 { 0xFFFFFF } else { 0x000000 })
-                    .collect();
-                window.update_with_buffer(&buffer, 64, 32).expect("Failed to update display");
-                self.draw_flag = false;
-            }
-            window.update();
+    pub fn keymap(&self) -> &KeyMap {
+        &self.keymap
+    }
+
+    pub fn run(&mut self, window: &mut Window, sound: &mut Sink) -> Result<(), Chip8Error> {
+        // Limit the window to 60 FPS for the timers, unless run_with paces itself
+        window.set_target_fps(if self.frame_pacing { 0 } else { 60 });
+        let mut frontend = WindowFrontend {
+            window,
+            keymap: self.keymap,
+        };
+        self.run_with(&mut frontend, sound)
+    }
+}
+
+// minifb window with the emulator's key bindings, Escape closes it and F1 toggles stats
+#[cfg(not(target_arch = "wasm32"))]
+struct WindowFrontend<'a> {
+    window: &'a mut Window,
+    keymap: KeyMap,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Renderer for WindowFrontend<'_> {
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+        // minifb expects a Vec<u32> where each u32 is 0x00RRGGBB
+        self.window
+            .update_with_buffer(buffer, width, height)
+            .expect("Failed to update display");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl InputSource for WindowFrontend<'_> {
+    fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+
+    fn pressed_keys(&mut self) -> [bool; 16] {
+        self.keymap.keys.map(|key| self.window.is_key_down(key))
+    }
+
+    fn stats_toggled(&mut self) -> bool {
+        self.window.is_key_pressed(Key::F1, KeyRepeat::No)
+    }
+
+    fn end_frame(&mut self) {
+        self.window.update();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AudioOutput for Sink {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping {
+            self.play();
+        } else {
+            self.pause();
         }
     }
 }
@@ -1,25 +1,99 @@
-use chip8_emulator::Chip8;
-use minifb::{Window, WindowOptions};
+use chip8_emulator::{Chip8, HEIGHT, InputScript, KeyMap, WIDTH, window_scale};
+use minifb::{Scale, Window, WindowOptions};
 use rodio::{source::SineWave, OutputStream, Sink, Source};
 use std::fs;
+use std::io::Read;
+
+const DEFAULT_SCALE: Scale = Scale::X16; // 64x32 up to 1024x512
+const KEYMAP_PATH: &str = "keymap.txt";
+const SCRIPT_TAIL_FRAMES: u64 = 60; // keep running a second after the last scripted key
+
+// `--scale N` picks the window size, N is 1, 2, 4, 8, 16 or 32
+fn scale_from_args(args: &[String]) -> Result<Scale, String> {
+    let Some(pos) = args.iter().position(|a| a == "--scale") else {
+        return Ok(DEFAULT_SCALE);
+    };
+    let value = args.get(pos + 1).ok_or("--scale needs a value")?;
+    let factor: u32 = value
+        .parse()
+        .map_err(|_| format!("invalid scale '{}'", value))?;
+    window_scale(factor).ok_or(format!(
+        "unsupported scale {}, use 1, 2, 4, 8, 16 or 32",
+        factor
+    ))
+}
+
+// `--script FILE` runs headless with scripted key input instead of opening a window,
+// FILE `-` reads the script from stdin
+fn script_from_args(args: &[String]) -> Result<Option<InputScript>, String> {
+    let Some(pos) = args.iter().position(|a| a == "--script") else {
+        return Ok(None);
+    };
+    let path = args.get(pos + 1).ok_or("--script needs a file")?;
+    let text = if path == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| format!("could not read script from stdin: {}", err))?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path, err))?
+    };
+    InputScript::parse(&text).map(Some).map_err(|err| err.to_string())
+}
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let scale = scale_from_args(&args).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    });
+    let script = script_from_args(&args).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    });
+
     let mut chip8 = Chip8::new();
+    // custom bindings are optional, fall back to the defaults when there's no file
+    if let Ok(text) = fs::read_to_string(KEYMAP_PATH) {
+        match KeyMap::parse(&text) {
+            Ok(keymap) => chip8.set_keymap(keymap),
+            Err(err) => {
+                eprintln!("Error in {}: {}", KEYMAP_PATH, err);
+                std::process::exit(1);
+            }
+        }
+    }
     let contents = fs::read("roms/Pong.ch8").expect("Could not read rom file");
-    chip8.load_rom(&contents);
+    if let Err(err) = chip8.try_load_rom(&contents) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+    if let Some(script) = script {
+        let frames = script.last_frame() + SCRIPT_TAIL_FRAMES;
+        if let Err(err) = chip8.run_scripted(&script, frames) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        chip8.debug_render_console();
+        return;
+    }
     let mut window = Window::new(
         "Chip-8 Emulator",
-        64,
-        32, // Internal resolution
+        WIDTH,
+        HEIGHT, // Internal resolution
         WindowOptions {
-            scale: minifb::Scale::X16, // Scale 64x32 up to 1024x512
+            scale,
             ..WindowOptions::default()
         },
     )
     .expect("Failed to create window");
     let (_stream, stream_handle) = OutputStream::try_default().expect("Failed to get audio output");
     let mut sound = create_sound(&stream_handle);
-    chip8.run(&mut window, &mut sound);
+    if let Err(err) = chip8.run(&mut window, &mut sound) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
 
 fn create_sound(handle: &rodio::OutputStreamHandle) -> Sink {
@@ -34,7 +108,15 @@ fn create_sound(handle: &rodio::OutputStreamHandle) -> Sink {
 
 #[cfg(test)]
 mod tests {
-    use crate::Chip8;
+    use crate::{Chip8, scale_from_args};
+    use chip8_emulator::{
+        Chip8Error, OpcodeFamily, OpcodeGroup, Platform, RegionKind, StateDiff, StepOutcome,
+    };
+
+    // the IBM logo test ROM, draws the six sprites of the striped logo with DXYN and then
+    // spins on a jump to itself at 0x228
+    const IBM_LOGO_ROM: &[u8; 132] = include_bytes!("../roms/IBM_Logo.ch8");
+    const IBM_LOGO_LIT_PIXELS: usize = 208;
 
     #[test]
     fn test_fetch() {
@@ -104,8 +186,8 @@ mod tests {
 
         // Digit '0' middle row (e.g., row 1) is 0x90 (1001 0000)
         // This means pixel (0,1) is 1 and (3,1) is 1.
-        let row_1_offset = 1 * 64;
-        assert_eq!(chip8.display[row_1_offset + 0], 1);
+        let row_1_offset = 64;
+        assert_eq!(chip8.display[row_1_offset], 1);
         assert_eq!(chip8.display[row_1_offset + 1], 0);
         assert_eq!(chip8.display[row_1_offset + 2], 0);
         assert_eq!(chip8.display[row_1_offset + 3], 1);
@@ -163,17 +245,8 @@ mod tests {
     fn test_ibm_logo_logic() {
         let mut chip8 = Chip8::new();
 
-        // Load the actual IBM Logo ROM bytes
-        // You can find these online or read from a file
-        let rom = [
-            0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x70, 0x09, 0xA2, 0x39,
-            0xD0, 0x1F, 0xA2, 0x48, 0x70, 0x08, 0xD0, 0x1F, 0x12, 0x16, 0x00, 0x00, 0x7C, 0x82,
-            0x82, 0x82, 0x7C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x1F, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F, 0x11,
-            0x11, 0x11, 0x1F, 0x11, 0x11, 0x11, 0x1F, 0x00, 0x00, 0x00,
-        ];
-        chip8.load_rom(&rom);
+        // Load the actual IBM Logo ROM bytes, the same file the builtin ibm_logo entry ships
+        chip8.load_rom(IBM_LOGO_ROM);
 
         // Run for about 20 cycles
         for _ in 0..20 {
@@ -184,4 +257,2056 @@ mod tests {
         // Print the result to your terminal!
         chip8.debug_render_console();
     }
+    #[test]
+    fn test_diff_reports_register_change() {
+        let left = Chip8::new();
+        let mut right = Chip8::new();
+        assert!(left.diff(&right).is_empty());
+
+        // 0x6105: LD V1, 5 only on the right side
+        right.decode_execute(0x6105);
+
+        assert_eq!(
+            left.diff(&right),
+            vec![StateDiff::Register { index: 1, left: 0, right: 5 }]
+        );
+    }
+    #[test]
+    fn test_entry_check_without_rom() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.check_entry(), Err(Chip8Error::NoRomLoaded));
+
+        chip8.load_rom(&[0x12, 0x00]);
+        assert_eq!(chip8.check_entry(), Ok(()));
+    }
+    #[test]
+    fn test_eti_660_entry_point() {
+        let mut chip8 = Chip8::new();
+        chip8.set_entry_point(chip8_emulator::ETI_660_ENTRY_POINT);
+        chip8.load_rom(&[0x12, 0x34]);
+        assert_eq!(chip8.pc, 0x600);
+        assert_eq!(chip8.fetch(), 0x1234);
+
+        // jumping back below the configured entry is reported, not a panic
+        chip8.pc = 0x200;
+        assert_eq!(
+            chip8.check_entry(),
+            Err(Chip8Error::EntryBelowMinimum { pc: 0x200, minimum: 0x600 })
+        );
+    }
+    #[test]
+    fn test_stats_overlay_changes_output() {
+        let mut chip8 = Chip8::new();
+        chip8.decode_execute(0xF029);
+        chip8.decode_execute(0xD005);
+        chip8.set_fps(60);
+        assert_eq!(chip8.stats().cycles, 2);
+
+        let plain = chip8.to_rgba();
+        chip8.set_show_stats(true);
+        let with_overlay = chip8.to_rgba();
+
+        assert_eq!(plain.len(), with_overlay.len());
+        assert_ne!(plain, with_overlay, "overlay should draw on top of the display");
+    }
+    #[test]
+    fn test_idle_throttle_drops_cycles() {
+        let mut chip8 = Chip8::new();
+        chip8.set_auto_idle_throttle(true);
+        // 0x1200: JP 0x200, the classic "halt" loop
+        chip8.load_rom(&[0x12, 0x00]);
+
+        assert_eq!(chip8.run_frame(), Ok(10), "first frame runs at full speed");
+        assert!(chip8.is_idle());
+        assert_eq!(chip8.run_frame(), Ok(1), "idle frames are throttled");
+
+        // any key press restores full speed
+        chip8.keypad[0x5] = true;
+        assert_eq!(chip8.run_frame(), Ok(10));
+    }
+    #[test]
+    fn test_idle_throttle_on_delay_timer_wait() {
+        let mut chip8 = Chip8::new();
+        chip8.set_auto_idle_throttle(true);
+        // 0x200: LD V0, 5 / LD DT, V0
+        // 0x204: LD V1, DT / SE V1, 0 / JP 0x204, the vblank style wait
+        // 0x20A: LD V2, 1 / JP 0x20C
+        chip8.load_rom(&[
+            0x60, 0x05, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04, 0x62, 0x01, 0x12, 0x0C,
+        ]);
+
+        assert_eq!(chip8.run_frame(), Ok(10), "first frame runs at full speed");
+        assert!(chip8.is_idle());
+        assert_eq!(chip8.run_frame(), Ok(3), "one pass through the loop per frame");
+        assert!(chip8.is_idle());
+        while chip8.vx[2] == 0 {
+            chip8.run_frame().unwrap();
+        }
+        assert_eq!(chip8.delay_timer(), 0);
+        // left the loop, the JP to itself is idle the usual way
+        assert!(chip8.is_idle());
+        assert_eq!(chip8.run_frame(), Ok(1));
+    }
+    #[test]
+    fn test_quirk_report_for_platform() {
+        let mut chip8 = Chip8::new();
+        assert!(chip8.quirk_report().starts_with("platform: default\n"));
+
+        chip8.set_platform(Platform::CosmacVip);
+        let report = chip8.quirk_report();
+        assert!(report.starts_with("platform: cosmac-vip\n"));
+        assert!(report.contains("shift_uses_vy: on\n"));
+        assert!(report.contains("jump_uses_vx: off\n"));
+
+        assert!(chip8.set_quirk("jump_uses_vx", true));
+        assert!(!chip8.set_quirk("no_such_quirk", true));
+        assert!(chip8.quirk_report().starts_with("platform: cosmac-vip (modified)\n"));
+    }
+    #[test]
+    fn test_quirks_change_opcode_behaviour() {
+        // 8XY1 clears VF with vf_reset
+        let mut chip8 = Chip8::new();
+        assert!(chip8.set_quirk("vf_reset", true));
+        chip8.vx[0xF] = 1;
+        chip8.decode_execute(0x8121); // OR V1, V2
+        assert_eq!(chip8.vx[0xF], 0);
+
+        // FX55 leaves I past the stored registers, seen through a second store
+        let mut chip8 = Chip8::new();
+        assert!(chip8.set_quirk("load_store_increments_i", true));
+        chip8.vx[0] = 0xAB;
+        chip8.decode_execute(0xA300); // LD I, 0x300
+        chip8.decode_execute(0xF155); // LD [I], V1
+        chip8.decode_execute(0xF055); // LD [I], V0
+        assert_eq!(chip8.ram[0x302], 0xAB);
+
+        // BNNN becomes BXNN
+        let mut chip8 = Chip8::new();
+        assert!(chip8.set_quirk("jump_uses_vx", true));
+        chip8.vx[2] = 4;
+        chip8.decode_execute(0xB220); // JP V2, 0x220
+        assert_eq!(chip8.pc, 0x224);
+
+        // a sprite cut off at the right edge doesn't come back in on the left
+        let mut chip8 = Chip8::new();
+        assert!(chip8.set_quirk("clip_sprites", true));
+        chip8.vx[0] = 62;
+        chip8.decode_execute(0xA050); // I = font glyph 0, top row 0xF0
+        chip8.decode_execute(0xD011); // DRW V0, V1, 1
+        chip8.decode_execute(0xD111); // DRW V1, V1, 1 at (0, 0)
+        assert_eq!(chip8.vx[0xF], 0);
+    }
+    #[test]
+    fn test_vip_shift_quirk_uses_vy() {
+        let mut chip8 = Chip8::new();
+        chip8.set_platform(Platform::CosmacVip);
+        chip8.vx[2] = 0x03;
+        chip8.decode_execute(0x8126); // SHR V1, V2
+        assert_eq!(chip8.vx[1], 0x01);
+        assert_eq!(chip8.vx[0xF], 1);
+    }
+    #[test]
+    fn test_draw_font_glyph_a() {
+        let mut chip8 = Chip8::new();
+        chip8.vx[0] = 0xA;
+        chip8.vx[1] = 0;
+        chip8.decode_execute(0xF029); // LD F, V0
+        chip8.decode_execute(0xD115); // DRW V1, V1, 5
+
+        // 'A' is F0 90 F0 90 90
+        let glyph_a: [[u8; 4]; 5] = [
+            [1, 1, 1, 1],
+            [1, 0, 0, 1],
+            [1, 1, 1, 1],
+            [1, 0, 0, 1],
+            [1, 0, 0, 1],
+        ];
+        for (y, row) in glyph_a.iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                assert_eq!(chip8.display[x + y * 64], pixel, "pixel ({}, {})", x, y);
+            }
+            assert_eq!(chip8.display[4 + y * 64], 0);
+        }
+    }
+    #[test]
+    fn test_font_region_is_write_protected() {
+        let mut chip8 = Chip8::new();
+        let font_before = chip8.ram[0x050..0x0A0].to_vec();
+
+        // point I at the font for '0' and dump registers over it
+        chip8.vx[0] = 0;
+        chip8.decode_execute(0xF029);
+        chip8.decode_execute(0xF155); // LD [I], V1
+        chip8.decode_execute(0xF033); // LD B, V0
+        assert_eq!(chip8.ram[0x050..0x0A0], font_before[..]);
+
+        chip8.set_protect_font(false);
+        chip8.decode_execute(0xF155);
+        assert_eq!(chip8.ram[0x050], 0);
+    }
+    #[test]
+    fn test_peek_opcode() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x34, 0x56, 0x78]);
+
+        assert_eq!(chip8.peek_opcode(0x200), 0x1234);
+        assert_eq!(chip8.peek_opcode(0x202), 0x5678);
+        assert_eq!(chip8.pc, 0x200, "peek must not move pc");
+
+        // the last byte of ram pairs with the first
+        chip8.ram[0xFFF] = 0xAB;
+        chip8.ram[0x000] = 0xCD;
+        assert_eq!(chip8.peek_opcode(0xFFF), 0xABCD);
+    }
+    #[test]
+    fn test_window_scale_mapping() {
+        use chip8_emulator::window_scale;
+        use minifb::Scale;
+
+        assert!(matches!(window_scale(1), Some(Scale::X1)));
+        assert!(matches!(window_scale(8), Some(Scale::X8)));
+        assert!(matches!(window_scale(16), Some(Scale::X16)));
+        assert!(matches!(window_scale(32), Some(Scale::X32)));
+        assert!(window_scale(0).is_none());
+        assert!(window_scale(3).is_none());
+
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(matches!(scale_from_args(&args(&["chip8"])), Ok(Scale::X16)));
+        assert!(matches!(scale_from_args(&args(&["chip8", "--scale", "8"])), Ok(Scale::X8)));
+        assert!(scale_from_args(&args(&["chip8", "--scale", "5"])).is_err());
+        assert!(scale_from_args(&args(&["chip8", "--scale"])).is_err());
+    }
+    #[test]
+    fn test_timer_rate_120hz() {
+        use std::time::Duration;
+
+        let mut chip8 = Chip8::new();
+        chip8.set_timer_rate_hz(120);
+        chip8.vx[0] = 200;
+        chip8.decode_execute(0xF015); // LD DT, V0
+
+        // half a second in 60Hz sized steps
+        let mut ticks = 0;
+        for _ in 0..30 {
+            ticks += chip8.advance_timers(Duration::from_nanos(16_666_667));
+        }
+        assert_eq!(ticks, 60);
+        assert_eq!(chip8.delay_timer(), 140);
+
+        // the default rate ticks once per frame
+        let mut chip8 = Chip8::new();
+        chip8.vx[0] = 10;
+        chip8.decode_execute(0xF015);
+        chip8.load_rom(&[0x12, 0x00]);
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.delay_timer(), 9);
+    }
+    #[test]
+    fn test_font_info() {
+        use chip8_emulator::{FONT_SET, FONT_START_ADDR};
+
+        let chip8 = Chip8::new();
+        let (start, stride) = chip8.font_info();
+        assert_eq!(start as usize, FONT_START_ADDR);
+        assert_eq!(stride, 5);
+
+        let start = start as usize;
+        assert_eq!(chip8.ram[start..start + 16 * stride], FONT_SET[..]);
+    }
+    #[test]
+    fn test_keymap_round_trip() {
+        use chip8_emulator::KeyMap;
+        use minifb::Key;
+
+        let mut keymap = KeyMap::default();
+        keymap.keys[0x1] = Key::Up;
+        keymap.keys[0x4] = Key::Down;
+        keymap.keys[0xC] = Key::NumPad8;
+
+        let text = keymap.to_string();
+        assert!(text.contains("1=Up\n"));
+        assert_eq!(KeyMap::parse(&text), Ok(keymap));
+
+        // partial files only override what they mention
+        let partial = KeyMap::parse("# arrows for player 1\n1 = Up\n").unwrap();
+        assert_eq!(partial.keys[0x1], Key::Up);
+        assert_eq!(partial.keys[0x4], KeyMap::default().keys[0x4]);
+    }
+    #[test]
+    fn test_keymap_rejects_malformed_input() {
+        use chip8_emulator::KeyMap;
+
+        assert!(matches!(
+            KeyMap::parse("1=W\nnonsense"),
+            Err(Chip8Error::InvalidKeyMap { line: 2, .. })
+        ));
+        assert!(KeyMap::parse("G=W").is_err(), "G is not a chip-8 key");
+        assert!(KeyMap::parse("10=W").is_err());
+        assert!(KeyMap::parse("1=NotAKey").is_err());
+        assert!(KeyMap::parse("1=Escape").is_err(), "Escape quits the emulator");
+    }
+    #[test]
+    fn test_render_side_by_side() {
+        use chip8_emulator::render::render_side_by_side;
+
+        let mut left = Chip8::new();
+        let mut right = Chip8::new();
+        left.decode_execute(0xF029); // '0' on the left
+        left.decode_execute(0xD005);
+        right.vx[0] = 0xA; // 'A' on the right
+        right.decode_execute(0xF029);
+        right.vx[0] = 0;
+        right.decode_execute(0xD005);
+
+        let combined = render_side_by_side(&[&left, &right]);
+        assert_eq!(combined.len(), 128 * 32);
+
+        let (left_frame, right_frame) = (left.to_rgba(), right.to_rgba());
+        for y in 0..32 {
+            let row = &combined[y * 128..(y + 1) * 128];
+            assert_eq!(row[..64], left_frame[y * 64..(y + 1) * 64]);
+            assert_eq!(row[64..], right_frame[y * 64..(y + 1) * 64]);
+        }
+    }
+    #[test]
+    fn test_unknown_opcodes_are_skipped_and_counted() {
+        let mut chip8 = Chip8::new();
+        // 5121, 8008, E000 and F0FF are all unknown, 6107 is LD V1, 7
+        let program = [0x51, 0x21, 0x80, 0x08, 0xE0, 0x00, 0xF0, 0xFF, 0x61, 0x07];
+        chip8.load_rom(&program);
+
+        for _ in 0..5 {
+            assert_eq!(chip8.step(), Ok(StepOutcome::Executed));
+        }
+        assert_eq!(chip8.unknown_opcode_count(), 4);
+        assert_eq!(chip8.pc, 0x20A);
+        assert_eq!(chip8.vx[1], 7);
+
+        // strict mode reports the first one instead
+        let mut chip8 = Chip8::new();
+        chip8.set_strict(true);
+        chip8.load_rom(&program);
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode { opcode: 0x5121, addr: 0x200 })
+        );
+    }
+    #[test]
+    fn test_pixel_helpers() {
+        let mut chip8 = Chip8::new();
+        chip8.decode_execute(0xF029);
+        chip8.decode_execute(0xD005);
+
+        // top row of '0' is 0xF0
+        assert!(chip8.pixel(0, 0));
+        assert!(chip8.pixel(1, 0));
+        assert!(chip8.pixel(2, 0));
+        assert!(chip8.pixel(3, 0));
+        assert!(!chip8.pixel(4, 0));
+        assert!(!chip8.pixel(64, 0), "off screen is never lit");
+
+        let bools = chip8.display_bools();
+        assert_eq!(bools.len(), 64 * 32);
+        assert_eq!(bools.iter().filter(|&&p| p).count(), 14);
+    }
+    #[test]
+    fn test_step_info_describes_instruction() {
+        use chip8_emulator::disassemble;
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x61, 0xC8, 0x81, 0x24, 0xD0, 0x15]);
+
+        let info = chip8.step_info().unwrap();
+        assert_eq!(info.pc, 0x200);
+        assert_eq!(info.opcode, 0x61C8);
+        assert_eq!(info.mnemonic, "LD V1, 0xC8");
+        assert_eq!(chip8.vx[1], 0xC8, "step_info executes the instruction");
+
+        assert_eq!(chip8.step_info().unwrap().mnemonic, "ADD V1, V2");
+        assert_eq!(chip8.step_info().unwrap().mnemonic, "DRW V0, V1, 5");
+        assert_eq!(disassemble(0x00E0), "CLS");
+        assert_eq!(disassemble(0xF265), "LD V2, [I]");
+        assert_eq!(disassemble(0x5121), "DW 0x5121");
+    }
+    #[test]
+    fn test_beep_threshold() {
+        let mut chip8 = Chip8::new();
+        chip8.vx[0] = 1;
+        chip8.decode_execute(0xF018); // LD ST, V0
+        assert!(chip8.is_beeping(), "default threshold beeps for any nonzero timer");
+
+        chip8.set_beep_threshold(1);
+        assert!(!chip8.is_beeping());
+
+        chip8.vx[0] = 2;
+        chip8.decode_execute(0xF018);
+        assert!(chip8.is_beeping());
+    }
+    #[test]
+    fn test_save_state_restores_rng() {
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(0xC8);
+        // C0FF: RND V0, 0xFF then loop back
+        chip8.load_rom(&[0xC0, 0xFF, 0x12, 0x00]);
+        for _ in 0..6 {
+            chip8.step().unwrap();
+        }
+
+        let saved = chip8.save_state();
+        let mut first_run = Vec::new();
+        for _ in 0..5 {
+            chip8.step().unwrap();
+            chip8.step().unwrap();
+            first_run.push(chip8.vx[0]);
+        }
+
+        chip8.load_state(&saved).unwrap();
+        let mut second_run = Vec::new();
+        for _ in 0..5 {
+            chip8.step().unwrap();
+            chip8.step().unwrap();
+            second_run.push(chip8.vx[0]);
+        }
+        assert_eq!(first_run, second_run);
+    }
+    #[test]
+    fn test_load_state_rejects_garbage() {
+        let mut chip8 = Chip8::new();
+        assert!(matches!(
+            chip8.load_state(b"nope"),
+            Err(Chip8Error::InvalidSaveState(_))
+        ));
+        let truncated = chip8.save_state()[..100].to_vec();
+        assert!(chip8.load_state(&truncated).is_err());
+    }
+    #[test]
+    fn test_crt_effect_dims_odd_scanlines() {
+        let mut chip8 = Chip8::new();
+        chip8.display.fill(1);
+        assert!(chip8.to_rgba().iter().all(|&c| c == 0xFFFFFF));
+
+        chip8.set_crt_effect(true);
+        let buffer = chip8.to_rgba();
+        for y in 0..32 {
+            let color = buffer[y * 64];
+            if y % 2 == 0 {
+                assert_eq!(color, 0xFFFFFF, "even line {} stays bright", y);
+            } else {
+                assert!(color & 0xFF < 0xFF, "odd line {} should be dimmer", y);
+            }
+        }
+    }
+    #[test]
+    fn test_step_n_trace() {
+        let mut chip8 = Chip8::new();
+        // same program as test_arithmetic_and_carry_flag
+        let program: [u8; 8] = [0x61, 0xC8, 0x62, 0x64, 0x71, 0x0A, 0x81, 0x24];
+        chip8.load_rom(&program);
+
+        let trace = chip8.step_n(4).unwrap();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace[3].pc, 0x206);
+        assert_eq!(trace[3].mnemonic, "ADD V1, V2");
+        assert_eq!(chip8.vx[1], 54);
+    }
+    #[test]
+    fn test_step_n_stops_at_breakpoint_and_halt() {
+        let mut chip8 = Chip8::new();
+        // LD V1, 1 / LD V2, 2 / JP 0x204
+        chip8.load_rom(&[0x61, 0x01, 0x62, 0x02, 0x12, 0x04]);
+        chip8.add_breakpoint(0x202);
+
+        assert_eq!(chip8.step_n(10).unwrap().len(), 1, "stops before the breakpoint");
+        assert_eq!(chip8.pc, 0x202);
+
+        // resuming from the breakpoint runs until the JP to itself
+        let trace = chip8.step_n(10).unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[1].mnemonic, "JP 0x204");
+    }
+    #[test]
+    fn test_shift_into_vf_keeps_flag() {
+        let mut chip8 = Chip8::new();
+
+        // SHR VF: 0x02 >> 1 = 0x01 but the bit shifted out is 0
+        chip8.vx[0xF] = 0x02;
+        chip8.decode_execute(0x8F06);
+        assert_eq!(chip8.vx[0xF], 0);
+
+        // 0x81 >> 1 = 0x40, shifted out bit is 1
+        chip8.vx[0xF] = 0x81;
+        chip8.decode_execute(0x8F06);
+        assert_eq!(chip8.vx[0xF], 1);
+
+        // SHL VF: 0x40 << 1 = 0x80 but the bit shifted out is 0
+        chip8.vx[0xF] = 0x40;
+        chip8.decode_execute(0x8F0E);
+        assert_eq!(chip8.vx[0xF], 0);
+
+        // 0x81 << 1 = 0x02, shifted out bit is 1
+        chip8.vx[0xF] = 0x81;
+        chip8.decode_execute(0x8F0E);
+        assert_eq!(chip8.vx[0xF], 1);
+    }
+    #[test]
+    fn test_sub_into_vf_keeps_flag() {
+        let mut chip8 = Chip8::new();
+        chip8.vx[0xF] = 10;
+        chip8.vx[1] = 3;
+        chip8.decode_execute(0x8F15); // SUB VF, V1 -> 7, no borrow
+        assert_eq!(chip8.vx[0xF], 1);
+
+        chip8.vx[0xF] = 3;
+        chip8.vx[1] = 10;
+        chip8.decode_execute(0x8F17); // SUBN VF, V1 -> 7, no borrow
+        assert_eq!(chip8.vx[0xF], 1);
+    }
+    #[test]
+    fn test_builtin_roms_load() {
+        use chip8_emulator::roms::{BUILTIN_ROMS, builtin_rom};
+
+        let names: Vec<&str> = BUILTIN_ROMS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["pong", "ibm_logo", "maze", "keypad"]);
+        for (name, data) in BUILTIN_ROMS {
+            let mut chip8 = Chip8::new();
+            chip8.set_strict(true);
+            chip8.load_rom(builtin_rom(name).expect("every table entry can be looked up"));
+            assert_eq!(chip8.check_entry(), Ok(()), "{} should load", name);
+            assert_eq!(chip8.ram[0x200..0x200 + data.len()], data[..]);
+            for _ in 0..30 {
+                chip8.run_frame().unwrap_or_else(|err| panic!("{} failed: {}", name, err));
+            }
+        }
+        assert!(builtin_rom("missing").is_none());
+    }
+    #[test]
+    fn test_is_display_blank() {
+        let mut chip8 = Chip8::new();
+        assert!(chip8.is_display_blank());
+
+        chip8.decode_execute(0xF029);
+        chip8.decode_execute(0xD005);
+        assert!(!chip8.is_display_blank());
+
+        chip8.decode_execute(0x00E0); // CLS
+        assert!(chip8.is_display_blank());
+    }
+    #[test]
+    fn test_load_store_wraps_at_top_of_ram() {
+        let mut chip8 = Chip8::new();
+        chip8.set_quirk("load_store_increments_i", true);
+        for (reg, value) in chip8.vx.iter_mut().enumerate() {
+            *value = reg as u8 + 1;
+        }
+        chip8.decode_execute(0xAFF8); // LD I, 0xFF8
+        chip8.decode_execute(0xFF55); // LD [I], VF
+
+        assert_eq!(chip8.ram[0xFF8..0x1000], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(chip8.ram[0x000..0x008], [9, 10, 11, 12, 13, 14, 15, 16]);
+
+        // I = 0xFF8 + 16 wraps to 0x008, read back from the wrapped address
+        chip8.vx = [0; 16];
+        chip8.decode_execute(0xAFF8);
+        chip8.decode_execute(0xFF65); // LD VF, [I]
+        assert_eq!(chip8.vx[15], 16);
+        chip8.ram[0x008] = 0x42;
+        chip8.decode_execute(0xF065); // LD V0, [I] with I already wrapped
+        assert_eq!(chip8.vx[0], 0x42);
+    }
+    #[test]
+    fn test_warm_and_cold_boot() {
+        let mut chip8 = Chip8::new();
+        chip8.set_protect_font(false);
+        chip8.load_rom(&[0x61, 0x05]);
+        chip8.ram[0x300] = 0xAB;
+        chip8.ram[0x050] = 0x00; // scribble on the font
+        chip8.step().unwrap();
+
+        chip8.reset();
+        assert_eq!(chip8.ram[0x300], 0xAB, "warm boot keeps ram");
+        assert_eq!(chip8.ram[0x050], 0xF0, "font is reloaded");
+        assert_eq!(chip8.vx[1], 0);
+        assert_eq!(chip8.pc, 0x200);
+        assert_eq!(chip8.ram[0x200], 0x61, "program survives a warm boot");
+
+        chip8.ram[0x050] = 0x00;
+        chip8.cold_boot();
+        assert_eq!(chip8.ram[0x300], 0, "cold boot zeroes ram");
+        assert_eq!(chip8.ram[0x200], 0);
+        assert_eq!(chip8.ram[0x050], 0xF0, "font is reloaded");
+        assert_eq!(chip8.check_entry(), Err(Chip8Error::NoRomLoaded));
+    }
+    #[test]
+    fn test_dimensions_follow_resolution() {
+        use chip8_emulator::{HEIGHT, HI_HEIGHT, HI_WIDTH, WIDTH};
+
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(chip8.dimensions(), (64, 32));
+
+        // 00FF is a plain SYS call unless SUPER-CHIP opcodes are on
+        chip8.decode_execute(0x00FF);
+        assert_eq!(chip8.dimensions(), (64, 32));
+
+        chip8.set_platform(Platform::SuperChip);
+        chip8.decode_execute(0x00FF); // HIGH
+        assert_eq!(chip8.dimensions(), (HI_WIDTH, HI_HEIGHT));
+        assert_eq!(chip8.dimensions(), (128, 64));
+        assert_eq!(chip8.to_rgba().len(), 128 * 64);
+
+        // sprites wrap at the hires edge, not the lores one
+        chip8.vx[0] = 100;
+        chip8.decode_execute(0xF129);
+        chip8.decode_execute(0xD015); // DRW V0, V1, 5 at (100, 0)
+        assert!(chip8.pixel(100, 0));
+
+        chip8.decode_execute(0x00FE); // LOW
+        assert_eq!(chip8.dimensions(), (64, 32));
+        assert!(chip8.is_display_blank());
+    }
+    #[test]
+    fn test_run_for_matches_clock() {
+        use std::time::Duration;
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x00]);
+        assert_eq!(chip8.clock_hz(), 600);
+
+        // a 144Hz display for one second, ~6.94ms per frame
+        let mut executed = 0;
+        for _ in 0..144 {
+            executed += chip8.run_for(Duration::from_micros(6_945)).unwrap();
+        }
+        assert!((599..=601).contains(&executed), "ran {} cycles", executed);
+
+        // a single huge gap is capped instead of running thousands of cycles
+        assert_eq!(chip8.run_for(Duration::from_secs(10)).unwrap(), 150);
+    }
+    #[test]
+    fn test_update_with_mock_clock() {
+        use chip8_emulator::Clock;
+        use std::sync::{Arc, Mutex};
+
+        struct MockClock(Arc<Mutex<f64>>);
+        impl Clock for MockClock {
+            fn now_ms(&self) -> f64 {
+                *self.0.lock().unwrap()
+            }
+        }
+
+        let time = Arc::new(Mutex::new(5_000.0));
+        let mut chip8 = Chip8::new();
+        chip8.set_clock(Box::new(MockClock(time.clone())));
+        chip8.load_rom(&[0x12, 0x00]);
+        chip8.vx[0] = 100;
+        chip8.decode_execute(0xF015); // LD DT, V0
+
+        assert_eq!(chip8.update(), Ok(0), "first update only starts the clock");
+
+        // one second in 100ms steps
+        let mut executed = 0;
+        for _ in 0..10 {
+            *time.lock().unwrap() += 100.0;
+            executed += chip8.update().unwrap();
+        }
+        assert_eq!(executed, 600);
+        assert_eq!(chip8.delay_timer(), 40);
+    }
+    #[test]
+    fn test_check_collision_latch() {
+        let mut chip8 = Chip8::new();
+        chip8.decode_execute(0xF029);
+
+        // the same sprite twice in the same place collides
+        chip8.decode_execute(0xD005);
+        chip8.decode_execute(0xD005);
+        // a later draw that doesn't collide resets VF but not the latch
+        chip8.vx[1] = 20;
+        chip8.decode_execute(0xD105);
+        assert_eq!(chip8.vx[0xF], 0);
+        assert!(chip8.check_collision());
+        assert!(!chip8.check_collision(), "reading clears the latch");
+
+        chip8.vx[1] = 40;
+        chip8.decode_execute(0xD105);
+        assert!(!chip8.check_collision());
+    }
+    #[test]
+    fn test_keypad_grid_layout() {
+        let mut chip8 = Chip8::new();
+        chip8.keypad[0xC] = true;
+        chip8.keypad[0x0] = true;
+
+        let grid = chip8.keypad_grid();
+        assert!(grid[0][3], "C is top right");
+        assert!(grid[3][1], "0 is bottom row, second column");
+        assert_eq!(grid.iter().flatten().filter(|&&k| k).count(), 2);
+    }
+    #[test]
+    fn test_set_display_checkerboard() {
+        use chip8_emulator::render::{OFF_COLOR, ON_COLOR};
+
+        let mut chip8 = Chip8::new();
+        let checkerboard: Vec<u8> = (0..64 * 32).map(|i| ((i % 64 + i / 64) % 2) as u8).collect();
+        chip8.set_display(&checkerboard).unwrap();
+        assert!(chip8.draw_flag);
+
+        let rgba = chip8.to_rgba();
+        for y in 0..32 {
+            for x in 0..64 {
+                let expected = if (x + y) % 2 == 1 { ON_COLOR } else { OFF_COLOR };
+                assert_eq!(rgba[x + y * 64], expected, "pixel ({}, {})", x, y);
+            }
+        }
+
+        assert_eq!(
+            chip8.set_display(&[0; 100]),
+            Err(Chip8Error::DisplaySizeMismatch { expected: 2048, actual: 100 })
+        );
+    }
+    #[test]
+    fn test_running_into_empty_memory_is_detected() {
+        // LD V1, 1 / LD V2, 2 and then nothing
+        let program = [0x61, 0x01, 0x62, 0x02];
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&program);
+        let trace = chip8.step_n(100).unwrap();
+        assert_eq!(trace.len(), 4, "stops after the second 0x0000");
+        assert!(chip8.is_runaway());
+
+        let mut chip8 = Chip8::new();
+        chip8.set_strict(true);
+        chip8.load_rom(&program);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap(); // a single 0x0000 is tolerated
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::ExecutingEmptyMemory { addr: 0x206 })
+        );
+    }
+    #[test]
+    fn test_flag_registers_persist_per_rom() {
+        let dir = std::env::temp_dir().join(format!("chip8_flags_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // LD V0, 0x42 / LD V1, 0x99 / LD R, V1
+        let rom = [0x60, 0x42, 0x61, 0x99, 0xF1, 0x75];
+
+        let mut chip8 = Chip8::new();
+        chip8.set_platform(Platform::SuperChip);
+        chip8.set_persistence_dir(&dir);
+        chip8.load_rom(&rom);
+        chip8.step_n(3).unwrap();
+
+        let mut fresh = Chip8::new();
+        fresh.set_platform(Platform::SuperChip);
+        fresh.set_persistence_dir(&dir);
+        fresh.load_rom(&rom);
+        assert_eq!(fresh.rom_hash(), chip8.rom_hash());
+        assert_eq!(fresh.rpl_flags()[..2], [0x42, 0x99]);
+        fresh.decode_execute(0xF185); // LD V1, R
+        assert_eq!(fresh.vx[..2], [0x42, 0x99]);
+
+        // a different ROM starts with empty flags
+        let mut other = Chip8::new();
+        other.set_persistence_dir(&dir);
+        other.load_rom(&[0x12, 0x00]);
+        assert_eq!(other.rpl_flags(), [0; 16]);
+
+        // even on the instance that just had the first ROM's flags loaded
+        fresh.load_rom(&[0x12, 0x00]);
+        assert_eq!(fresh.rpl_flags(), [0; 16]);
+        fresh.decode_execute(0xF075); // LD R, V0 saves the second ROM's flags
+        fresh.load_rom(&rom);
+        assert_eq!(fresh.rpl_flags()[..2], [0x42, 0x99]);
+        fresh.load_rom(&[0x12, 0x00]);
+        assert_eq!(fresh.rpl_flags(), [0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_to_is_deterministic_across_save_restore() {
+        let rom = chip8_emulator::roms::builtin_rom("pong").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(7);
+        chip8.load_rom(rom);
+
+        chip8.run_to(50).unwrap();
+        assert_eq!(chip8.stats().cycles, 50);
+        let saved = chip8.save_state();
+        let hash_at_50 = chip8.display_hash();
+
+        chip8.run_to(100).unwrap();
+        assert_eq!(chip8.stats().cycles, 100);
+        let hash_at_100 = chip8.display_hash();
+
+        chip8.load_state(&saved).unwrap();
+        assert_eq!(chip8.stats().cycles, 50);
+        assert_eq!(chip8.display_hash(), hash_at_50);
+        chip8.run_to(100).unwrap();
+        assert_eq!(chip8.display_hash(), hash_at_100);
+    }
+
+    #[test]
+    fn test_to_rgba_scaled_maps_pixels_to_blocks() {
+        let mut chip8 = Chip8::new();
+        chip8.display[0] = 1;
+        chip8.display[3 + 2 * 64] = 1;
+        let buffer = chip8.to_rgba_scaled(2);
+        assert_eq!(buffer.len(), 128 * 64);
+
+        let frame = chip8.to_rgba();
+        for y in 0..64 {
+            for x in 0..128 {
+                assert_eq!(buffer[x + y * 128], frame[x / 2 + (y / 2) * 64]);
+            }
+        }
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1), (6, 4), (7, 4), (6, 5), (7, 5)] {
+            assert_eq!(buffer[x + y * 128], chip8_emulator::render::ON_COLOR);
+        }
+        assert_eq!(buffer[2], chip8_emulator::render::OFF_COLOR);
+    }
+
+    #[test]
+    fn test_frame_blend_shows_erased_pixel_at_half_intensity() {
+        let mut chip8 = Chip8::new();
+        chip8.set_frame_blend(true);
+        chip8.load_rom(&[0x00, 0xE0, 0x12, 0x02]); // CLS then spin
+        chip8.display[0] = 1;
+
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.display[0], 0);
+        assert_eq!(chip8.to_rgba()[0], 0x7F7F7F);
+
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.to_rgba()[0], chip8_emulator::render::OFF_COLOR);
+
+        chip8.set_frame_blend(false);
+        chip8.display[0] = 1;
+        assert_eq!(chip8.to_rgba()[0], chip8_emulator::render::ON_COLOR);
+    }
+
+    #[test]
+    fn test_supported_opcodes_follow_platform() {
+        let mut chip8 = Chip8::new();
+        let opcodes = chip8.supported_opcodes();
+        assert!(opcodes.contains(&("00E0 CLS", OpcodeFamily::Core)));
+        assert!(opcodes.contains(&("DXYN DRW Vx, Vy, n", OpcodeFamily::Core)));
+        assert!(opcodes.iter().all(|&(_, family)| family == OpcodeFamily::Core));
+        assert!(!opcodes.iter().any(|&(name, _)| name.starts_with("00CN")));
+
+        chip8.set_platform(Platform::SuperChip);
+        let opcodes = chip8.supported_opcodes();
+        assert!(opcodes.contains(&("00FF HIGH", OpcodeFamily::SuperChip)));
+        assert!(opcodes.contains(&("00CN SCD n", OpcodeFamily::SuperChip)));
+        assert!(opcodes.contains(&("00E0 CLS", OpcodeFamily::Core)));
+    }
+
+    #[test]
+    fn test_key_skips_use_low_nibble_of_vx() {
+        let mut chip8 = Chip8::new();
+        chip8.vx[0] = 200; // 0xC8, key 8
+        chip8.keypad[8] = true;
+        chip8.pc = 0x200;
+        chip8.decode_execute(0xE09E); // SKP V0
+        assert_eq!(chip8.pc, 0x202);
+        chip8.decode_execute(0xE0A1); // SKNP V0
+        assert_eq!(chip8.pc, 0x202);
+
+        chip8.keypad[8] = false;
+        chip8.decode_execute(0xE0A1);
+        assert_eq!(chip8.pc, 0x204);
+    }
+
+    #[test]
+    fn test_timers_advance_by_cycles() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.clock_hz(), 600);
+        chip8.decode_execute(0x600A); // LD V0, 10
+        chip8.decode_execute(0xF015); // LD DT, V0
+        assert_eq!(chip8.advance_timers_by_cycles(120), 12);
+        assert_eq!(chip8.delay_timer(), 0);
+
+        chip8.decode_execute(0xF015);
+        chip8.advance_timers_by_cycles(55);
+        assert_eq!(chip8.delay_timer(), 5);
+        chip8.advance_timers_by_cycles(5); // the leftover 5 cycles carry over
+        assert_eq!(chip8.delay_timer(), 4);
+    }
+
+    #[test]
+    fn test_cycle_timed_timers_count_down_inside_a_frame() {
+        // LD V0, 10 / LD DT, V0 / JP 0x204
+        let rom = [0x60, 0x0A, 0xF0, 0x15, 0x12, 0x04];
+        let mut per_frame = Chip8::new();
+        per_frame.load_rom(&rom);
+        per_frame.run_to(12).unwrap();
+        assert_eq!(per_frame.delay_timer(), 10);
+
+        let mut per_cycle = Chip8::new();
+        per_cycle.set_cycle_timed_timers(true);
+        per_cycle.load_rom(&rom);
+        per_cycle.run_to(12).unwrap();
+        assert_eq!(per_cycle.delay_timer(), 9);
+
+        // a whole frame only ticks once either way
+        per_cycle.run_frame().unwrap();
+        assert_eq!(per_cycle.delay_timer(), 8);
+    }
+
+    #[test]
+    fn test_memory_regions_cover_ram() {
+        let mut chip8 = Chip8::new();
+        let regions = chip8.memory_regions();
+        let font = regions
+            .iter()
+            .find(|region| region.kind == RegionKind::Font)
+            .unwrap();
+        assert_eq!((font.start, font.end - font.start), (0x050, 80));
+
+        // regions are contiguous from 0 to the end of ram
+        assert_eq!(regions[0].start, 0);
+        assert!(regions.windows(2).all(|pair| pair[0].end == pair[1].start));
+        let program = regions.last().unwrap();
+        assert_eq!(
+            (program.start, program.end, program.kind),
+            (0x200, 0x1000, RegionKind::Program)
+        );
+
+        chip8.set_entry_point(chip8_emulator::ETI_660_ENTRY_POINT);
+        assert_eq!(chip8.memory_regions().last().unwrap().start, 0x600);
+    }
+
+    #[test]
+    fn test_disabled_opcode_group_is_unknown() {
+        let mut chip8 = Chip8::new();
+        chip8.set_opcode_group_enabled(OpcodeGroup::Shift, false);
+        chip8.vx[0] = 0b11;
+        chip8.decode_execute(0x8006); // SHR V0
+        assert_eq!(chip8.vx[0], 0b11);
+        assert_eq!(chip8.unknown_opcode_count(), 1);
+
+        chip8.set_strict(true);
+        chip8.load_rom(&[0x80, 0x0E]); // SHL V0
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode {
+                opcode: 0x800E,
+                addr: 0x200
+            })
+        );
+
+        chip8.set_opcode_group_enabled(OpcodeGroup::Shift, true);
+        chip8.decode_execute(0x8006);
+        assert_eq!(chip8.vx[0], 0b1);
+    }
+
+    #[test]
+    fn test_frame_into_fills_preallocated_buffer() {
+        let mut chip8 = Chip8::new();
+        chip8.display[5] = 1;
+        chip8.display[64 * 31 + 63] = 1;
+        let mut frame = vec![0u8; 64 * 32];
+        chip8.frame_into(&mut frame).unwrap();
+        assert_eq!(frame, chip8.active_display());
+
+        let mut wrong_size = vec![0u8; 10];
+        assert_eq!(
+            chip8.frame_into(&mut wrong_size),
+            Err(Chip8Error::DisplaySizeMismatch {
+                expected: 2048,
+                actual: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_manual_clock_runs_without_real_time() {
+        use std::time::Duration;
+        let clock = chip8_emulator::ManualClock::new();
+        let mut chip8 = Chip8::new();
+        chip8.set_clock(Box::new(clock.clone()));
+        // LD V0, 0xFF / LD DT, V0 / JP 0x204
+        chip8.load_rom(&[0x60, 0xFF, 0xF0, 0x15, 0x12, 0x04]);
+
+        let started = std::time::Instant::now();
+        chip8.update().unwrap();
+        // four seconds of frames, thousands of instructions
+        let mut executed = 0;
+        for _ in 0..240 {
+            clock.advance(Duration::from_nanos(16_666_667));
+            executed += chip8.update().unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(executed, 2400);
+        assert_eq!(chip8.delay_timer(), 0xFF - 240);
+    }
+
+    #[test]
+    fn test_call_depth_follows_call_and_ret() {
+        let mut chip8 = Chip8::new();
+        // 0x200: CALL 0x206 / JP 0x202 / unused
+        // 0x206: CALL 0x20A / RET
+        // 0x20A: RET
+        chip8.load_rom(&[
+            0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x0A, 0x00, 0xEE, 0x00, 0xEE,
+        ]);
+        assert_eq!(chip8.call_depth(), 0);
+        chip8.step_n(2).unwrap();
+        assert_eq!(chip8.call_depth(), 2);
+        assert_eq!(chip8.pc, 0x20A);
+        chip8.step_n(2).unwrap();
+        assert_eq!(chip8.call_depth(), 0);
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_vf_accumulate_collisions_keeps_vf_across_draws() {
+        let mut chip8 = Chip8::new();
+        chip8.set_quirk("vf_accumulate_collisions", true);
+        chip8.decode_execute(0xA050); // I = font glyph 0
+        chip8.decode_execute(0xD015); // draw at (0, 0)
+        chip8.decode_execute(0xD015); // erase it again, collides
+        assert_eq!(chip8.vx[0xF], 1);
+
+        chip8.vx[0] = 20; // somewhere empty, no collision
+        chip8.decode_execute(0xD015);
+        assert_eq!(chip8.vx[0xF], 1);
+
+        // standard behaviour resets VF on every draw
+        chip8.set_quirk("vf_accumulate_collisions", false);
+        chip8.vx[0] = 40;
+        chip8.decode_execute(0xD015);
+        assert_eq!(chip8.vx[0xF], 0);
+    }
+
+    #[test]
+    fn test_export_octo_sprites_reproduces_drawing() {
+        let mut chip8 = Chip8::new();
+        chip8.decode_execute(0xA050); // I = font glyph 0
+        chip8.vx[0] = 3;
+        chip8.vx[1] = 2;
+        chip8.decode_execute(0xD015);
+        assert_eq!(
+            chip8.export_octo_sprites(3, 2, 8, 5),
+            ": sprite_3_2\n  0xF0 0x90 0x90 0x90 0xF0\n"
+        );
+
+        // 16 pixels wide and 20 tall splits into four tiles
+        let export = chip8.export_octo_sprites(0, 0, 16, 20);
+        assert_eq!(export.matches(": sprite_").count(), 4);
+        assert!(export.starts_with(": sprite_0_0\n  0x00 0x00 0x1E 0x12 0x12 0x12 0x1E"));
+    }
+
+    #[test]
+    fn test_on_beep_start_fires_on_rising_edge() {
+        use std::sync::{Arc, Mutex};
+
+        let beeps = Arc::new(Mutex::new(Vec::new()));
+        let mut chip8 = Chip8::new();
+        let recorded = Arc::clone(&beeps);
+        chip8.set_on_beep_start(move |duration| recorded.lock().unwrap().push(duration));
+
+        chip8.vx[0] = 20;
+        chip8.decode_execute(0xF018); // LD ST, V0
+        assert_eq!(*beeps.lock().unwrap(), [20]);
+
+        // already beeping, so no new start
+        chip8.decode_execute(0xF018);
+        assert_eq!(*beeps.lock().unwrap(), [20]);
+    }
+
+    #[test]
+    fn test_dxyn_sprite_reads_wrap_past_end_of_ram() {
+        let mut chip8 = Chip8::new();
+        chip8.ram[0xFFD] = 0x80;
+        chip8.ram[0xFFF] = 0x80;
+        chip8.ram[0x000] = 0xFF; // row 3 wraps around to address 0
+        chip8.decode_execute(0xAFFD); // LD I, 0xFFD
+        chip8.decode_execute(0xD01A); // DRW V0, V1, 10
+        assert!(chip8.pixel(0, 0));
+        assert!(!chip8.pixel(0, 1));
+        assert!(chip8.pixel(0, 2));
+        assert!((0..8).all(|x| chip8.pixel(x, 3)));
+    }
+
+    #[test]
+    fn test_compare_trace_reports_first_divergence() {
+        use chip8_emulator::{Divergence, TraceEntry, parse_trace};
+
+        // LD V0, 1 / ADD V0, 1 / JP 0x202
+        let rom = [0x60, 0x01, 0x70, 0x01, 0x12, 0x02];
+        let reference = parse_trace(
+            "# pc opcode
+            0200 6001
+            0202 7001
+            0204 1202
+            0202 7001",
+        )
+        .unwrap();
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom);
+        assert_eq!(chip8.compare_trace(&reference), Ok(None));
+        assert_eq!(chip8.vx[0], 3);
+
+        let mut diverged = reference.clone();
+        diverged[3] = TraceEntry {
+            pc: 0x206,
+            opcode: 0x0000,
+        };
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom);
+        assert_eq!(
+            chip8.compare_trace(&diverged),
+            Ok(Some(Divergence {
+                cycle: 3,
+                expected: diverged[3],
+                actual: reference[3],
+            }))
+        );
+
+        assert!(matches!(
+            parse_trace("0200"),
+            Err(Chip8Error::InvalidTrace { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_instruction_cache_handles_self_modifying_code() {
+        // 0x200: LD I, 0x20A / LD V0, 0x70 / LD V1, 0x05 / LD [I], V1
+        // 0x208: JP 0x20A
+        // 0x20A: LD V2, 0x01, rewritten to ADD V0, 0x05 by the FX55 above
+        let rom = [
+            0xA2, 0x0A, 0x60, 0x70, 0x61, 0x05, 0xF1, 0x55, 0x12, 0x0A, 0x62, 0x01, 0x12, 0x0C,
+        ];
+        let mut cached = Chip8::new();
+        cached.set_instruction_cache(true);
+        cached.load_rom(&rom);
+        cached.step_n(6).unwrap();
+
+        let mut plain = Chip8::new();
+        plain.load_rom(&rom);
+        plain.step_n(6).unwrap();
+
+        assert_eq!(cached.vx[0], 0x75);
+        assert_eq!(cached.vx[2], 0);
+        assert!(cached.diff(&plain).is_empty());
+    }
+
+    #[test]
+    fn test_display_diff_image_marks_changed_pixels() {
+        const CHANGED: u32 = 0xFF0000;
+        let blank = Chip8::new();
+        let mut drawn = Chip8::new();
+        drawn.decode_execute(0xA050); // I = font glyph 0
+        drawn.decode_execute(0xD015);
+
+        let diff = blank.display_diff_image(&drawn, CHANGED, 0xFFFFFF, 0x000000);
+        assert_eq!(diff.len(), 64 * 32);
+        for (idx, &color) in diff.iter().enumerate() {
+            let expected = if drawn.display[idx] == 1 { CHANGED } else { 0 };
+            assert_eq!(color, expected);
+        }
+        assert_eq!(diff.iter().filter(|&&color| color == CHANGED).count(), 14);
+
+        let same = drawn.display_diff_image(&drawn, CHANGED, 0xFFFFFF, 0x000000);
+        assert_eq!(same[0], 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_input_script_applies_keys_at_frames() {
+        use chip8_emulator::InputScript;
+
+        let script = InputScript::parse(
+            "frame 2: press 1; frame 4: release 1
+            # second key on its own line
+            frame 3: press A",
+        )
+        .unwrap();
+        assert_eq!(script.events().len(), 3);
+        assert_eq!(script.last_frame(), 4);
+
+        // LD V0, K / JP 0x202
+        let rom = [0xF0, 0x0A, 0x12, 0x02];
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom);
+        chip8.run_scripted(&script, 2).unwrap();
+        assert_eq!(chip8.pc, 0x200); // still waiting for a key
+        chip8.load_rom(&rom);
+        chip8.run_scripted(&script, 3).unwrap();
+        assert_eq!(chip8.vx[0], 1);
+        assert!(chip8.keypad[1]);
+        chip8.run_scripted(&script, 5).unwrap();
+        assert!(!chip8.keypad[1]);
+        assert!(chip8.keypad[0xA]);
+
+        assert_eq!(
+            InputScript::parse("frame 1: press 1; frame x: press 2"),
+            Err(Chip8Error::InvalidInputScript {
+                entry: 2,
+                reason: "'x' is not a frame number".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_or_draw_mode_keeps_pixels_on() {
+        use chip8_emulator::DrawMode;
+
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.draw_mode(), DrawMode::Xor);
+        chip8.set_draw_mode(DrawMode::Or);
+        chip8.decode_execute(0xA050); // I = font glyph 0
+        chip8.decode_execute(0xD015);
+        chip8.decode_execute(0xD015);
+        assert!(chip8.pixel(0, 0));
+        assert_eq!(chip8.vx[0xF], 1); // the overlap still counts as a collision
+
+        chip8.set_draw_mode(DrawMode::Xor);
+        chip8.decode_execute(0xD015);
+        assert!(!chip8.pixel(0, 0));
+    }
+
+    #[test]
+    fn test_needs_redraw_until_marked_drawn() {
+        let mut chip8 = Chip8::new();
+        chip8.mark_drawn();
+        assert!(!chip8.needs_redraw());
+
+        chip8.decode_execute(0x00E0); // CLS
+        assert!(chip8.needs_redraw());
+        chip8.mark_drawn();
+        assert!(!chip8.needs_redraw());
+
+        chip8.decode_execute(0xD015);
+        assert!(chip8.needs_redraw());
+        chip8.mark_drawn();
+        chip8.decode_execute(0x6001); // LD V0, 1 doesn't touch the screen
+        assert!(!chip8.needs_redraw());
+    }
+
+    #[test]
+    fn test_unhandled_report_counts_distinct_opcodes() {
+        let mut chip8 = Chip8::new();
+        // 0x200: unknown 0x5121 / unknown 0xE000 / JP 0x200
+        chip8.load_rom(&[0x51, 0x21, 0xE0, 0x00, 0x12, 0x00]);
+        chip8.run_to(30).unwrap();
+        assert_eq!(chip8.unhandled_report(), vec![(0x5121, 10), (0xE000, 10)]);
+        assert_eq!(chip8.unknown_opcode_count(), 20);
+
+        chip8.reset();
+        assert!(chip8.unhandled_report().is_empty());
+    }
+
+    #[test]
+    fn test_read_opcode_is_big_endian_and_wraps() {
+        use chip8_emulator::read_opcode;
+
+        assert_eq!(read_opcode(&[0xA2, 0xF0], 0), 0xA2F0);
+        assert_eq!(read_opcode(&[], 0), 0);
+
+        let mut chip8 = Chip8::new();
+        chip8.ram[0xFFF] = 0x12;
+        chip8.ram[0x000] = 0x34;
+        assert_eq!(read_opcode(&chip8.ram, 0xFFF), 0x1234);
+        assert_eq!(chip8.peek_opcode(0xFFF), 0x1234);
+        chip8.pc = 0xFFF;
+        assert_eq!(chip8.fetch(), 0x1234);
+    }
+
+    #[test]
+    fn test_stack_size_limits_call_depth() {
+        // CALL 0x202 / CALL 0x204 / CALL 0x206 ..., every step nests one level deeper
+        let deep_rom: Vec<u8> = (0..20u16)
+            .flat_map(|n| (0x2000 | (0x202 + n * 2)).to_be_bytes())
+            .collect();
+
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.stack_size(), 16);
+        chip8.set_stack_size(32);
+        chip8.load_rom(&deep_rom);
+        chip8.run_to(20).unwrap();
+        assert_eq!(chip8.call_depth(), 20);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&deep_rom);
+        chip8.run_to(16).unwrap();
+        assert_eq!(chip8.call_depth(), 16);
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::StackOverflow {
+                addr: 0x220,
+                depth: 16
+            })
+        );
+        assert_eq!(chip8.call_depth(), 16);
+
+        // the stack size travels with save states
+        let mut restored = Chip8::new();
+        restored.load_state(&chip8.save_state()).unwrap();
+        assert_eq!(restored.stack_size(), 16);
+    }
+
+    #[test]
+    fn test_break_on_unknown_pauses_with_state_intact() {
+        let mut chip8 = Chip8::new();
+        chip8.set_break_on_unknown(true);
+        // LD V0, 5 / unknown 0x5121 / LD V0, 9
+        chip8.load_rom(&[0x60, 0x05, 0x51, 0x21, 0x60, 0x09]);
+        assert_eq!(chip8.step(), Ok(StepOutcome::Executed));
+        let before = chip8.save_state();
+
+        assert_eq!(chip8.step(), Ok(StepOutcome::UnknownOpcode(0x5121)));
+        assert_eq!(chip8.vx[0], 5);
+        assert_eq!(chip8.pc, 0x204);
+        let mut expected = Chip8::new();
+        expected.load_state(&before).unwrap();
+        expected.pc = 0x204;
+        assert!(chip8.diff(&expected).is_empty());
+
+        // run_frame stops at the unknown opcode instead of running the whole frame
+        chip8.load_rom(&[0x60, 0x05, 0x51, 0x21, 0x60, 0x09]);
+        assert_eq!(chip8.run_frame(), Ok(2));
+        assert_eq!(chip8.vx[0], 5);
+    }
+
+    #[test]
+    fn test_display_to_string_renders_logo() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(IBM_LOGO_ROM);
+        chip8.run_frame().unwrap();
+        chip8.run_frame().unwrap();
+
+        let text = chip8.display_to_string('#', '.');
+        assert_eq!(text.lines().count(), 32);
+        assert!(text.lines().all(|line| line.chars().count() == 64));
+        assert_eq!(text.matches('#').count(), IBM_LOGO_LIT_PIXELS);
+        // the top stripe of the I, B and M
+        let row_8 = text.lines().nth(8).unwrap();
+        assert_eq!(&row_8[12..], "########.#########...#####.........#####............");
+    }
+
+    #[test]
+    fn test_fx0a_selection_with_several_keys_held() {
+        use chip8_emulator::Fx0aMode;
+
+        let mut chip8 = Chip8::new();
+        chip8.set_key(5, true);
+        chip8.set_key(2, true);
+        chip8.decode_execute(0xF00A); // LD V0, K
+        assert_eq!(chip8.vx[0], 2);
+
+        chip8.set_fx0a_selection(Fx0aMode::MostRecent);
+        chip8.decode_execute(0xF00A);
+        assert_eq!(chip8.vx[0], 2);
+        chip8.set_key(5, false);
+        chip8.set_key(5, true);
+        chip8.decode_execute(0xF00A);
+        assert_eq!(chip8.vx[0], 5);
+
+        // key 0 counts as well
+        let mut chip8 = Chip8::new();
+        chip8.set_key(0, true);
+        chip8.pc = 0x202;
+        chip8.decode_execute(0xF10A);
+        assert_eq!((chip8.vx[1], chip8.pc), (0, 0x202));
+    }
+
+    #[test]
+    fn test_run_with_mock_frontend_presents_logo() {
+        use chip8_emulator::render::ON_COLOR;
+        use chip8_emulator::{AudioOutput, InputSource, ManualClock, Renderer};
+        use std::time::Duration;
+
+        #[derive(Default)]
+        struct MockWindow {
+            frames_left: u32,
+            presented: Vec<Vec<u32>>,
+            clock: ManualClock,
+        }
+        impl Renderer for MockWindow {
+            fn present(&mut self, buffer: &[u32], width: usize, height: usize) {
+                assert_eq!(buffer.len(), width * height);
+                self.presented.push(buffer.to_vec());
+            }
+        }
+        impl InputSource for MockWindow {
+            fn is_open(&self) -> bool {
+                self.frames_left > 0
+            }
+            fn pressed_keys(&mut self) -> [bool; 16] {
+                self.clock.advance(Duration::from_nanos(16_666_667));
+                [false; 16]
+            }
+            fn end_frame(&mut self) {
+                self.frames_left -= 1;
+            }
+        }
+        struct MockAudio(Vec<bool>);
+        impl AudioOutput for MockAudio {
+            fn set_beeping(&mut self, beeping: bool) {
+                self.0.push(beeping);
+            }
+        }
+
+        // every frame is a 60th of a second on the clock
+        let clock = ManualClock::new();
+        let mut chip8 = Chip8::new();
+        chip8.set_clock(Box::new(clock.clone()));
+        chip8.load_rom(IBM_LOGO_ROM);
+        let mut window = MockWindow {
+            frames_left: 10,
+            clock,
+            ..MockWindow::default()
+        };
+        let mut audio = MockAudio(Vec::new());
+        chip8.run_with(&mut window, &mut audio).unwrap();
+
+        assert_eq!(chip8.stats().cycles, 10 * 10);
+        assert_eq!(audio.0, [false; 10]);
+        // the logo is done after two frames, the idle frames after it aren't presented
+        assert!(!window.presented.is_empty() && window.presented.len() < 10);
+        let last = window.presented.last().unwrap();
+        let lit = last.iter().filter(|&&color| color == ON_COLOR).count();
+        assert_eq!(lit, IBM_LOGO_LIT_PIXELS);
+    }
+
+    #[test]
+    fn test_step_frame_reports_drawing() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(IBM_LOGO_ROM);
+        chip8.mark_drawn();
+        let outcomes: Vec<_> = (0..4).map(|_| chip8.step_frame().unwrap()).collect();
+
+        assert!(!chip8.is_display_blank());
+        assert!(outcomes.iter().any(|outcome| outcome.drew));
+        assert!(!outcomes[3].drew); // spinning on the final jump by then
+        assert!(outcomes.iter().all(|outcome| outcome.cycles == 10 && !outcome.beeping));
+        assert!(chip8.needs_redraw());
+    }
+
+    #[test]
+    fn test_00cn_scrolls_display_down() {
+        let mut chip8 = Chip8::new();
+        chip8.set_platform(Platform::SuperChip);
+        chip8.display[3] = 1; // (3, 0)
+        chip8.display[64 * 31] = 1; // (0, 31), scrolled off the bottom
+
+        chip8.decode_execute(0x00C0); // scroll by 0 changes nothing
+        assert!(chip8.pixel(3, 0) && chip8.pixel(0, 31));
+
+        chip8.decode_execute(0x00C4);
+        assert!(!chip8.pixel(3, 0));
+        assert!(chip8.pixel(3, 4));
+        assert!(!chip8.pixel(0, 31));
+
+        chip8.decode_execute(0x00CF);
+        assert!(chip8.pixel(3, 19));
+        assert_eq!(chip8.active_display().iter().filter(|&&p| p == 1).count(), 1);
+
+        // without SUPER-CHIP it's just a SYS call
+        let mut chip8 = Chip8::new();
+        chip8.display[3] = 1;
+        chip8.decode_execute(0x00C4);
+        assert!(chip8.pixel(3, 0));
+        assert_eq!(chip8.unknown_opcode_count(), 0);
+    }
+
+    #[test]
+    fn test_pacing_sleep_fills_rest_of_frame() {
+        use chip8_emulator::pacing_sleep;
+        use std::time::Duration;
+
+        assert_eq!(pacing_sleep(100.0, 104.0), Duration::from_nanos(12_666_667));
+        assert_eq!(pacing_sleep(100.0, 100.0), Duration::from_nanos(16_666_667));
+        // a slow frame doesn't sleep at all
+        assert_eq!(pacing_sleep(100.0, 120.0), Duration::ZERO);
+        assert_eq!(pacing_sleep(100.0, 90.0), Duration::from_nanos(16_666_667));
+    }
+
+    #[test]
+    fn test_load_asm_runs_carry_program() {
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_asm("LD V1, 200\nLD V2, 100\nADD V1, 10\nADD V1, V2")
+            .unwrap();
+        assert_eq!(chip8.pc, 0x200);
+        chip8.step_n(4).unwrap();
+        assert_eq!(chip8.vx[1], 54);
+        assert_eq!(chip8.vx[0xF], 1);
+
+        // every mnemonic disassemble prints assembles back to the same opcode
+        let core = [0x00E0, 0x1204, 0x8126, 0xA2F0, 0xB123, 0xD015, 0xF133, 0xF265, 0x5121];
+        // SUPER-CHIP and the 01NN debug extension
+        let extended = [0x00C4, 0x00FE, 0x00FF, 0xD120, 0xF375, 0xF485, 0x0140];
+        for opcode in core.into_iter().chain(extended) {
+            let text = chip8_emulator::disassemble(opcode);
+            assert_eq!(chip8_emulator::asm::assemble(&text), Ok(opcode.to_be_bytes().to_vec()));
+        }
+
+        assert_eq!(
+            chip8.load_asm("CLS\n    LD V1, 300 ; too big"),
+            Err(chip8_emulator::AsmError {
+                line: 2,
+                column: 5,
+                reason: "300 doesn't fit in a byte".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_last_draw_bounds_tracks_latest_sprite() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.last_draw_bounds(), None);
+        chip8.decode_execute(0xA050); // I = font glyph 0
+        chip8.decode_execute(0xD015);
+        assert_eq!(chip8.last_draw_bounds(), Some((0, 0, 8, 5)));
+
+        chip8.vx[0] = 70; // wraps to x 6
+        chip8.vx[1] = 3;
+        chip8.decode_execute(0xD013);
+        assert_eq!(chip8.last_draw_bounds(), Some((6, 3, 8, 3)));
+    }
+
+    #[test]
+    fn test_native_and_web_timing_match_for_same_dt_schedule() {
+        use chip8_emulator::{AudioOutput, InputSource, ManualClock, Renderer};
+        use std::time::Duration;
+
+        // uneven frame times like a browser or a window that misses vsync now and then
+        const SCHEDULE_MS: [f64; 8] = [16.7, 8.3, 33.4, 16.6, 6.9, 50.0, 16.7, 11.1];
+        // LD V0, 0xFF / LD DT, V0 / LD ST, V0 / ADD V1, 1 / JP 0x206
+        const ROM: [u8; 10] = [0x60, 0xFF, 0xF0, 0x15, 0xF0, 0x18, 0x71, 0x01, 0x12, 0x06];
+
+        // native: run_with moves along the clock, the mock window sets it each frame
+        struct ScheduledWindow {
+            clock: ManualClock,
+            frame: usize,
+        }
+        impl Renderer for ScheduledWindow {
+            fn present(&mut self, _buffer: &[u32], _width: usize, _height: usize) {}
+        }
+        impl InputSource for ScheduledWindow {
+            fn is_open(&self) -> bool {
+                self.frame < SCHEDULE_MS.len()
+            }
+            fn pressed_keys(&mut self) -> [bool; 16] {
+                let dt = SCHEDULE_MS[self.frame];
+                self.clock.advance(Duration::from_secs_f64(dt / 1000.0));
+                [false; 16]
+            }
+            fn end_frame(&mut self) {
+                self.frame += 1;
+            }
+        }
+        struct Silent;
+        impl AudioOutput for Silent {
+            fn set_beeping(&mut self, _beeping: bool) {}
+        }
+
+        let clock = ManualClock::new();
+        let mut native = Chip8::new();
+        native.set_clock(Box::new(clock.clone()));
+        native.load_rom(&ROM);
+        let mut window = ScheduledWindow { clock, frame: 0 };
+        native.run_with(&mut window, &mut Silent).unwrap();
+
+        // web: the wasm wrapper advances its own clock by the dt JS hands to tick_with_dt
+        let clock = ManualClock::new();
+        let mut web = Chip8::new();
+        web.set_clock(Box::new(clock.clone()));
+        web.update().unwrap();
+        web.load_rom(&ROM);
+        for dt in SCHEDULE_MS {
+            clock.advance(Duration::from_secs_f64(dt / 1000.0));
+            web.update().unwrap();
+        }
+
+        let total_ms: f64 = SCHEDULE_MS.iter().sum();
+        assert_eq!(native.stats().cycles, (total_ms * 0.6) as u64);
+        assert_eq!(native.stats().cycles, web.stats().cycles);
+        assert_eq!(native.vx[1], web.vx[1]);
+        assert_eq!(native.delay_timer(), web.delay_timer());
+        assert_eq!(native.sound_timer(), web.sound_timer());
+        assert_eq!(native.delay_timer(), 0xFF - (total_ms * 0.06) as u8);
+    }
+
+    #[test]
+    fn test_register_map_after_load() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x00]);
+        let map = chip8.register_map();
+        assert_eq!(map.len(), 21);
+        assert_eq!(map["PC"], 0x200);
+        for index in 0..16 {
+            assert_eq!(map[&format!("V{:X}", index)], 0);
+        }
+        assert_eq!((map["I"], map["SP"], map["DT"], map["ST"]), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_7xnn_never_touches_vf() {
+        let mut chip8 = Chip8::new();
+        chip8.vx[0xF] = 1;
+        // every register but VF and every immediate, plenty of them overflow
+        for x in 0..0xF_u16 {
+            for nn in 0..=0xFF_u16 {
+                chip8.decode_execute(0x7000 | (x << 8) | nn);
+                assert_eq!(chip8.vx[0xF], 1, "ADD V{:X}, {:#04x} changed VF", x, nn);
+            }
+        }
+    }
+
+    #[test]
+    fn test_debug_extension_sets_speed() {
+        let mut chip8 = Chip8::new();
+        chip8.decode_execute(0x0140);
+        assert_eq!(chip8.cycles_per_tick(), 10, "a plain SYS without the extensions");
+
+        chip8.set_debug_extensions(true);
+        chip8.decode_execute(0x0140);
+        assert_eq!(chip8.cycles_per_tick(), 0x40);
+        chip8.decode_execute(0x0100);
+        assert_eq!(chip8.cycles_per_tick(), 0x40);
+    }
+
+    #[test]
+    fn test_rng_log_replays_without_seed() {
+        // RND V0..V5 with a few different masks
+        let rnd = [0xC0FF, 0xC10F, 0xC2F0, 0xC3FF, 0xC455, 0xC5FF];
+
+        let mut recorded = Chip8::new();
+        recorded.set_seed(1234);
+        recorded.set_rng_logging(true);
+        for &opcode in &rnd {
+            recorded.decode_execute(opcode);
+        }
+        let log = recorded.rng_log().to_vec();
+        assert_eq!(log.len(), rnd.len());
+
+        let mut replayed = Chip8::new();
+        replayed.set_seed(99);
+        replayed.replay_rng(&log);
+        for &opcode in &rnd {
+            replayed.decode_execute(opcode);
+        }
+        assert_eq!(replayed.vx, recorded.vx);
+
+        // once the log runs out it's the seeded rng again
+        let mut seeded = Chip8::new();
+        seeded.set_seed(99);
+        seeded.decode_execute(0xC6FF);
+        replayed.decode_execute(0xC6FF);
+        assert_eq!(replayed.vx[6], seeded.vx[6]);
+    }
+
+    #[test]
+    fn test_try_load_rom_rejects_tiny_roms() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.try_load_rom(&[]), Err(Chip8Error::RomTooSmall { len: 0 }));
+        assert_eq!(chip8.try_load_rom(&[0x60]), Err(Chip8Error::RomTooSmall { len: 1 }));
+        assert_eq!(chip8.check_entry(), Err(Chip8Error::NoRomLoaded));
+
+        // an odd sized ROM doesn't inherit the byte after it from the previous one
+        chip8.try_load_rom(&[0x60, 0x11, 0x61, 0x22]).unwrap();
+        chip8.try_load_rom(&[0x60, 0x33, 0x61]).unwrap();
+        assert_eq!(chip8.peek_opcode(0x202), 0x6100);
+    }
+
+    #[test]
+    fn test_list_and_clear_breakpoints_and_watchpoints() {
+        use chip8_emulator::WatchSpec;
+        let mut chip8 = Chip8::new();
+        // LD V0, 1 / LD V3, 7 / LD V0, 2 / JP 0x206
+        chip8.load_rom(&[0x60, 0x01, 0x63, 0x07, 0x60, 0x02, 0x12, 0x06]);
+        chip8.add_breakpoint(0x206);
+        chip8.add_breakpoint(0x204);
+        chip8.add_watchpoint(WatchSpec::Register(3));
+        assert_eq!(chip8.breakpoints(), [0x204, 0x206]);
+        assert_eq!(chip8.watchpoints(), [WatchSpec::Register(3)]);
+
+        // stops right after LD V3, 7 changes the watched register
+        assert_eq!(chip8.step_n(10).unwrap().len(), 2);
+
+        chip8.clear_all_breakpoints();
+        assert!(chip8.breakpoints().is_empty() && chip8.watchpoints().is_empty());
+        assert_eq!(chip8.step_n(10).unwrap().len(), 2, "runs on to the JP to itself");
+    }
+
+    #[test]
+    fn test_pc_wraps_at_top_of_ram() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x00]);
+        chip8.pc = 0x0FFE;
+        chip8.fetch();
+        assert_eq!(chip8.pc, 0x000);
+        assert_eq!(chip8.fetch(), chip8.peek_opcode(0x000));
+        assert_eq!(chip8.pc, 0x002);
+
+        // a skip on the last instruction lands past the top and wraps too
+        chip8.pc = 0x0FFE;
+        chip8.ram[0xFFE] = 0x30; // SE V0, 0
+        chip8.ram[0xFFF] = 0x00;
+        chip8.step().unwrap();
+        assert_eq!(chip8.pc, 0x002);
+    }
+
+    #[test]
+    fn test_pixel_sink_gets_sprite_pixels() {
+        use chip8_emulator::PixelSink;
+
+        #[derive(Default)]
+        struct MockLeds(Vec<(usize, usize, bool)>);
+        impl PixelSink for MockLeds {
+            fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+                self.0.push((x, y, on));
+            }
+        }
+
+        let mut chip8 = Chip8::new();
+        let mut leds = MockLeds::default();
+        chip8.send_pixel_changes(&mut leds);
+        assert_eq!(leds.0.len(), 64 * 32, "the first call sends the whole screen");
+
+        // glyph 1 at (10, 4): 0x20 0x60 0x20 0x20 0x70
+        leds.0.clear();
+        chip8.vx[0] = 10;
+        chip8.vx[1] = 4;
+        chip8.decode_execute(0xA055);
+        chip8.decode_execute(0xD015);
+        chip8.send_pixel_changes(&mut leds);
+        let lit = [
+            (12, 4),
+            (11, 5),
+            (12, 5),
+            (12, 6),
+            (12, 7),
+            (11, 8),
+            (12, 8),
+            (13, 8),
+        ];
+        let expected: Vec<_> = lit.iter().map(|&(x, y)| (x, y, true)).collect();
+        assert_eq!(leds.0, expected);
+
+        // drawing it again erases exactly those pixels
+        leds.0.clear();
+        chip8.decode_execute(0xD015);
+        chip8.send_pixel_changes(&mut leds);
+        let erased: Vec<_> = lit.iter().map(|&(x, y)| (x, y, false)).collect();
+        assert_eq!(leds.0, erased);
+    }
+
+    #[test]
+    fn test_cosmac_lfsr_follows_the_vip_routine() {
+        use chip8_emulator::RngAlgorithm;
+        let mut chip8 = Chip8::new();
+        chip8.set_rng_algorithm(RngAlgorithm::CosmacLfsr);
+        chip8.ram[0x100..0x104].copy_from_slice(&[0x45, 0xA3, 0x98, 0x56]);
+        let rnd = |chip8: &mut Chip8| {
+            chip8.decode_execute(0xC0FF); // RND V0, 0xFF
+            chip8.vx[0]
+        };
+
+        // R9 = 0x12FF, INC R9 = 0x1300, 0x13 + page[0x00] 0x45 = 0x58 no carry,
+        // 0x58 + (0x58 >> 1) = 0x84 becomes R9.1
+        // R9 = 0x8401, 0x84 + 0xA3 = 0x27 carry, 0x27 + 0x93 = 0xBA
+        // R9 = 0xBA02, 0xBA + 0x98 = 0x52 carry, 0x52 + 0xA9 = 0xFB
+        // R9 = 0xFB03, 0xFB + 0x56 = 0x51 carry, 0x51 + 0xA8 = 0xF9
+        chip8.set_seed(0x12FF);
+        let bytes: Vec<u8> = (0..4).map(|_| rnd(&mut chip8)).collect();
+        assert_eq!(bytes, [0x84, 0xBA, 0xFB, 0xF9]);
+
+        // a timer tick bumps R9 like the VIP's interrupt did, page[0x01] is read instead
+        // R9 = 0x1301, 0x13 + 0xA3 = 0xB6 no carry, 0xB6 + 0x5B = 0x11
+        chip8.set_seed(0x12FF);
+        chip8.tick_timers();
+        assert_eq!(rnd(&mut chip8), 0x11);
+
+        // the mask is applied after R9.1 is updated
+        chip8.set_seed(0x12FF);
+        chip8.decode_execute(0xC00F);
+        assert_eq!(chip8.vx[0], 0x04);
+        assert_eq!(rnd(&mut chip8), 0xBA);
+    }
+
+    #[test]
+    fn test_cycles_until_timer_event() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.cycles_until_timer_event(), None);
+
+        // 600Hz cpu and 60Hz timers, 10 instructions per tick
+        chip8.vx[0] = 3;
+        chip8.decode_execute(0xF015); // LD DT, V0
+        chip8.vx[1] = 8;
+        chip8.decode_execute(0xF118); // LD ST, V1
+        assert_eq!(chip8.cycles_until_timer_event(), Some(30));
+
+        // running them down with cycle timed timers lands exactly on zero
+        chip8.set_cycle_timed_timers(true);
+        chip8.advance_timers_by_cycles(29);
+        assert_eq!(chip8.cycles_until_timer_event(), Some(1));
+        chip8.advance_timers_by_cycles(1);
+        assert_eq!(chip8.delay_timer(), 0);
+        assert_eq!(chip8.cycles_until_timer_event(), Some(50), "the sound timer is next");
+    }
+
+    #[test]
+    fn test_phosphor_fade_decays_after_pixel_turns_off() {
+        use chip8_emulator::render::PhosphorFade;
+        let mut chip8 = Chip8::new();
+        let mut fade = PhosphorFade::new();
+        chip8.decode_execute(0xA050);
+        chip8.decode_execute(0xD001); // one row of glyph 0, pixel (0, 0) lit
+        assert_eq!(fade.apply(chip8.active_display(), 100)[0], 255);
+
+        chip8.decode_execute(0xD001); // erased again
+        let levels: Vec<u8> = (0..4)
+            .map(|_| fade.apply(chip8.active_display(), 100)[0])
+            .collect();
+        assert_eq!(levels, [155, 55, 0, 0]);
+        assert_eq!(fade.apply(chip8.active_display(), 100)[10], 0, "never lit");
+    }
+
+    #[test]
+    fn test_freeze_rng_gives_fixed_bytes_until_thawed() {
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(7);
+        chip8.freeze_rng();
+        for _ in 0..5 {
+            chip8.decode_execute(0xC0FF);
+            assert_eq!(chip8.vx[0], chip8_emulator::FROZEN_RANDOM_BYTE);
+        }
+
+        // the seeded sequence picks up where it was, as if the freeze never happened
+        chip8.thaw_rng();
+        let mut unfrozen = Chip8::new();
+        unfrozen.set_seed(7);
+        let mut values = Vec::new();
+        for _ in 0..5 {
+            chip8.decode_execute(0xC0FF);
+            unfrozen.decode_execute(0xC0FF);
+            assert_eq!(chip8.vx[0], unfrozen.vx[0]);
+            values.push(chip8.vx[0]);
+        }
+        values.dedup();
+        assert!(values.len() > 1);
+    }
+
+    #[test]
+    fn test_code_extent_of_logo_rom() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(IBM_LOGO_ROM);
+        let (start, end) = chip8.code_extent();
+        assert_eq!(start, 0x200);
+        // the code ends with the JP at 0x228, the 90 bytes of sprite data after it aren't code
+        assert_eq!(end, 0x229);
+        assert_eq!(0x200 + IBM_LOGO_ROM.len() as u16 - 1 - end, 90);
+
+        // LD I, 0x206 / DRW V0, V0, 1 / JP 0x204 followed by one byte of sprite data
+        chip8.cold_boot();
+        chip8.load_rom(&[0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF]);
+        assert_eq!(chip8.code_extent(), (0x200, 0x205));
+    }
+
+    #[test]
+    fn test_replay_bundle_reproduces_session() {
+        use chip8_emulator::{InputScript, Replay};
+
+        // waits for a key with FX0A, then draws a random byte's glyph where the key says
+        // LD V0, K / RND V1, 0x0F / LD F, V1 / DRW V0, V0, 5 / JP 0x200
+        let rom = vec![0xF0, 0x0A, 0xC1, 0x0F, 0xF1, 0x29, 0xD0, 0x05, 0x12, 0x00];
+        let script = InputScript::parse(
+            "frame 2: press 5\nframe 4: release 5\nframe 6: press A\nframe 8: release A",
+        )
+        .unwrap();
+        let replay = Replay {
+            seed: 0x1234,
+            rom,
+            frames: 12,
+            script,
+        };
+        let mut original = Chip8::new();
+        original.play_replay(&replay).unwrap();
+
+        let bundle = replay.to_string();
+        let parsed = Replay::parse(&bundle).unwrap();
+        assert_eq!(parsed, replay);
+        let mut replayed = Chip8::new();
+        replayed.set_seed(99); // overridden by the bundle
+        replayed.play_replay(&parsed).unwrap();
+        assert_eq!(replayed.display_hash(), original.display_hash());
+        assert_ne!(original.display_hash(), Chip8::new().display_hash());
+
+        let tampered = bundle.replace("rom f00a", "rom f10a");
+        assert!(matches!(
+            Replay::parse(&tampered),
+            Err(Chip8Error::InvalidReplay(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_state_restores_vip_rng_replay_and_accumulators() {
+        use chip8_emulator::RngAlgorithm;
+        use std::time::Duration;
+        let random_run = |chip8: &mut Chip8| -> Vec<u8> {
+            (0..6)
+                .map(|_| {
+                    chip8.decode_execute(0xC0FF);
+                    chip8.vx[0]
+                })
+                .collect()
+        };
+
+        let mut chip8 = Chip8::new();
+        chip8.set_rng_algorithm(RngAlgorithm::CosmacLfsr);
+        chip8.ram[0x100..0x200].fill(0x5A); // something for the VIP routine to mix in
+        chip8.set_seed(42);
+        random_run(&mut chip8);
+        chip8.replay_rng(&[1, 2, 3]);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.vx[0] = 0x99;
+        chip8.decode_execute(0xF075); // LD R, V0
+        chip8.advance_timers(Duration::from_millis(10)); // leaves most of a tick over
+        let saved = chip8.save_state();
+
+        let vip_run = random_run(&mut chip8);
+        chip8.freeze_rng();
+        let frozen_state = chip8.save_state();
+
+        chip8.load_state(&saved).unwrap();
+        assert_eq!(random_run(&mut chip8), vip_run);
+        assert_eq!(chip8.rpl_flags()[0], 0x99);
+        chip8.vx[0] = 1;
+        chip8.decode_execute(0xF015); // LD DT, V0
+        assert_eq!(chip8.advance_timers(Duration::from_millis(7)), 1, "10ms + 7ms is a tick");
+
+        chip8.load_state(&frozen_state).unwrap();
+        assert_eq!(random_run(&mut chip8), [0; 6]);
+    }
+
+    #[test]
+    fn test_chip8_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Chip8>();
+
+        // and can actually run on another thread
+        let mut chip8 = Chip8::new();
+        chip8.set_clock(Box::new(chip8_emulator::ManualClock::new()));
+        chip8.load_rom(&[0x60, 0x07, 0x12, 0x02]);
+        let handle = std::thread::spawn(move || {
+            chip8.run_frame().unwrap();
+            chip8
+        });
+        assert_eq!(handle.join().unwrap().vx[0], 7);
+    }
+
+    #[test]
+    fn test_instruction_cache_covers_only_the_rom() {
+        // 0x200: LD V0, 0x01 / JP 0x204 / JP 0x206 written by the host past the ROM
+        let mut chip8 = Chip8::new();
+        chip8.set_instruction_cache(true);
+        chip8.load_rom(&[0x60, 0x01, 0x12, 0x04]);
+        chip8.ram[0x204] = 0x12;
+        chip8.ram[0x205] = 0x06;
+        chip8.step_n(3).unwrap();
+        assert_eq!((chip8.vx[0], chip8.pc), (0x01, 0x206));
+
+        // patch the ROM to LD V0, 0x42, a direct write needs an invalidate
+        chip8.ram[0x201] = 0x42;
+        chip8.invalidate_instruction_cache();
+        chip8.pc = 0x200;
+        chip8.step_n(1).unwrap();
+        assert_eq!(chip8.vx[0], 0x42);
+    }
+
+    #[test]
+    fn test_step_n_stops_on_unknown_opcode_and_errors() {
+        // LD V0, 5 / unknown 0x5121 / LD V0, 9
+        let rom = [0x60, 0x05, 0x51, 0x21, 0x60, 0x09];
+        let mut chip8 = Chip8::new();
+        chip8.set_break_on_unknown(true);
+        chip8.load_rom(&rom);
+        let trace = chip8.step_n(10).unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[1].outcome, StepOutcome::UnknownOpcode(0x5121));
+        assert_eq!(chip8.vx[0], 5);
+
+        let mut strict = Chip8::new();
+        strict.set_strict(true);
+        strict.load_rom(&rom);
+        assert!(strict.step_n(10).is_err());
+        assert_eq!(strict.vx[0], 5);
+    }
+
+    #[test]
+    fn test_fx0a_at_top_of_ram_waits_in_place() {
+        // LD V0, K at 0xFFE, fetch has already wrapped pc to 0 when it backs up
+        let mut chip8 = Chip8::new();
+        chip8.ram[0xFFE] = 0xF0;
+        chip8.ram[0xFFF] = 0x0A;
+        chip8.pc = 0xFFE;
+        chip8.step().unwrap();
+        assert_eq!(chip8.pc, 0xFFE);
+    }
+
+    #[test]
+    fn test_skip_at_top_of_ram_wraps() {
+        // SE V0, 0x00 at 0xFFC, fetch leaves pc at 0xFFE and the skip carries on at 0
+        let mut chip8 = Chip8::new();
+        chip8.ram[0xFFC] = 0x30;
+        chip8.pc = 0xFFC;
+        chip8.step().unwrap();
+        assert_eq!(chip8.pc, 0x000);
+
+        chip8.pc = 0xFFE;
+        chip8.set_key(0, true);
+        chip8.decode_execute(0xE09E); // SKP V0
+        assert_eq!(chip8.pc, 0x000);
+    }
+
+    #[test]
+    fn test_load_replay_queues_input_and_resets_rng() {
+        use chip8_emulator::{InputScript, Replay};
+
+        // LD V0, K / RND V1, 0x0F / LD F, V1 / DRW V0, V0, 5 / JP 0x200
+        let replay = Replay {
+            seed: 0x1234,
+            rom: vec![0xF0, 0x0A, 0xC1, 0x0F, 0xF1, 0x29, 0xD0, 0x05, 0x12, 0x00],
+            frames: 8,
+            script: InputScript::parse("frame 2: press 5\nframe 4: release 5").unwrap(),
+        };
+        let path = std::env::temp_dir().join(format!("chip8_replay_{}", std::process::id()));
+        replay.save(&path).unwrap();
+        let mut original = Chip8::new();
+        original.play_replay(&replay).unwrap();
+
+        // rng state left over from an earlier session mustn't leak into the replay
+        let mut loaded = Chip8::new();
+        loaded.replay_rng(&[0x0A, 0x0B]);
+        loaded.freeze_rng();
+        let frames = loaded.load_replay(&path).unwrap().frames;
+        for _ in 0..frames {
+            loaded.run_frame().unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.display_hash(), original.display_hash());
+        assert_ne!(loaded.display_hash(), Chip8::new().display_hash());
+    }
+
+    #[test]
+    fn test_ret_with_empty_stack_is_an_error() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x00, 0xEE]); // RET
+        assert_eq!(chip8.step(), Err(Chip8Error::StackUnderflow { addr: 0x200 }));
+        assert_eq!((chip8.call_depth(), chip8.pc), (0, 0x202));
+
+        // CALL 0x204 / JP 0x202 / RET still returns normally
+        chip8.load_rom(&[0x22, 0x04, 0x12, 0x02, 0x00, 0xEE]);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!((chip8.call_depth(), chip8.pc), (0, 0x202));
+    }
 }
@@ -0,0 +1,132 @@
+use crate::Chip8;
+
+// which instruction set an opcode comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpcodeFamily {
+    Core,
+    SuperChip,
+    XoChip,
+}
+
+// finer grouping by what an instruction does, old interpreters differ in whole groups
+// (no shifts, no key waits...) so these are what set_opcode_group_enabled switches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpcodeGroup {
+    Flow,       // 0NNN 00EE 1NNN 2NNN BNNN
+    Skip,       // 3XNN 4XNN 5XY0 9XY0
+    Load,       // 6XNN 7XNN 8XY0 ANNN CXNN
+    Logic,      // 8XY1 8XY2 8XY3
+    Arithmetic, // 8XY4 8XY5 8XY7
+    Shift,      // 8XY6 8XYE
+    Display,    // 00E0 00CN 00FE 00FF DXYN
+    Keypad,     // EX9E EXA1 FX0A
+    Timers,     // FX07 FX15 FX18
+    Memory,     // FX1E FX29 FX33 FX55 FX65 FX75 FX85
+}
+
+// None for opcodes that don't decode to anything
+pub fn opcode_group(opcode: u16) -> Option<OpcodeGroup> {
+    let nibbles = (
+        (opcode & 0xF000) >> 12,
+        (opcode & 0x0F00) >> 8,
+        (opcode & 0x00F0) >> 4,
+        opcode & 0x000F,
+    );
+    let group = match nibbles {
+        (0x0, 0x0, 0xE, 0x0) | (0x0, 0x0, 0xF, 0xE) | (0x0, 0x0, 0xF, 0xF) => OpcodeGroup::Display,
+        (0x0, 0x0, 0xC, _) => OpcodeGroup::Display,
+        (0x0, _, _, _) | (0x1, _, _, _) | (0x2, _, _, _) | (0xB, _, _, _) => OpcodeGroup::Flow,
+        (0x3, _, _, _) | (0x4, _, _, _) | (0x5, _, _, 0x0) | (0x9, _, _, 0x0) => OpcodeGroup::Skip,
+        (0x6, _, _, _) | (0x7, _, _, _) | (0x8, _, _, 0x0) => OpcodeGroup::Load,
+        (0xA, _, _, _) | (0xC, _, _, _) => OpcodeGroup::Load,
+        (0x8, _, _, 0x1..=0x3) => OpcodeGroup::Logic,
+        (0x8, _, _, 0x4) | (0x8, _, _, 0x5) | (0x8, _, _, 0x7) => OpcodeGroup::Arithmetic,
+        (0x8, _, _, 0x6) | (0x8, _, _, 0xE) => OpcodeGroup::Shift,
+        (0xD, _, _, _) => OpcodeGroup::Display,
+        (0xE, _, 0x9, 0xE) | (0xE, _, 0xA, 0x1) | (0xF, _, 0x0, 0xA) => OpcodeGroup::Keypad,
+        (0xF, _, 0x0, 0x7) | (0xF, _, 0x1, 0x5) | (0xF, _, 0x1, 0x8) => OpcodeGroup::Timers,
+        (0xF, _, 0x1, 0xE) | (0xF, _, 0x2, 0x9) | (0xF, _, 0x3, 0x3) => OpcodeGroup::Memory,
+        (0xF, _, 0x5, 0x5) | (0xF, _, 0x6, 0x5) => OpcodeGroup::Memory,
+        (0xF, _, 0x7, 0x5) | (0xF, _, 0x8, 0x5) => OpcodeGroup::Memory,
+        _ => return None,
+    };
+    Some(group)
+}
+
+// opcode pattern followed by its mnemonic, same spelling as disassemble()
+const CORE_OPCODES: [&str; 35] = [
+    "00E0 CLS",
+    "00EE RET",
+    "0NNN SYS addr",
+    "1NNN JP addr",
+    "2NNN CALL addr",
+    "3XNN SE Vx, byte",
+    "4XNN SNE Vx, byte",
+    "5XY0 SE Vx, Vy",
+    "6XNN LD Vx, byte",
+    "7XNN ADD Vx, byte",
+    "8XY0 LD Vx, Vy",
+    "8XY1 OR Vx, Vy",
+    "8XY2 AND Vx, Vy",
+    "8XY3 XOR Vx, Vy",
+    "8XY4 ADD Vx, Vy",
+    "8XY5 SUB Vx, Vy",
+    "8XY6 SHR Vx",
+    "8XY7 SUBN Vx, Vy",
+    "8XYE SHL Vx",
+    "9XY0 SNE Vx, Vy",
+    "ANNN LD I, addr",
+    "BNNN JP V0, addr",
+    "CXNN RND Vx, byte",
+    "DXYN DRW Vx, Vy, n",
+    "EX9E SKP Vx",
+    "EXA1 SKNP Vx",
+    "FX07 LD Vx, DT",
+    "FX0A LD Vx, K",
+    "FX15 LD DT, Vx",
+    "FX18 LD ST, Vx",
+    "FX1E ADD I, Vx",
+    "FX29 LD F, Vx",
+    "FX33 LD B, Vx",
+    "FX55 LD [I], Vx",
+    "FX65 LD Vx, [I]",
+];
+
+const SUPERCHIP_OPCODES: [&str; 6] = [
+    "00CN SCD n",
+    "00FE LOW",
+    "00FF HIGH",
+    "DXY0 DRW Vx, Vy, 0",
+    "FX75 LD R, Vx",
+    "FX85 LD Vx, R",
+];
+
+impl Chip8 {
+    // a disabled group goes down the unknown opcode path, so it's skipped with a message
+    // or is an error in strict mode, like on an interpreter that never had it
+    pub fn set_opcode_group_enabled(&mut self, group: OpcodeGroup, enabled: bool) {
+        if enabled {
+            self.disabled_opcodes.remove(&group);
+        } else {
+            self.disabled_opcodes.insert(group);
+        }
+    }
+
+    // every instruction the emulator will execute with its current settings, e.g. the
+    // SUPER-CHIP ones only show up once that platform or quirk is on
+    // nothing from XO-CHIP is implemented yet
+    pub fn supported_opcodes(&self) -> Vec<(&'static str, OpcodeFamily)> {
+        let mut opcodes: Vec<(&'static str, OpcodeFamily)> = CORE_OPCODES
+            .iter()
+            .map(|&name| (name, OpcodeFamily::Core))
+            .collect();
+        if self.quirks.superchip_opcodes {
+            opcodes.extend(
+                SUPERCHIP_OPCODES
+                    .iter()
+                    .map(|&name| (name, OpcodeFamily::SuperChip)),
+            );
+        }
+        opcodes
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::Chip8;
+
+// keeps the SUPER-CHIP flag registers (FX75/FX85) on disk between sessions, one file per
+// ROM named after its hash, which is how SCHIP games save high scores
+impl Chip8 {
+    // flags for the loaded ROM are read right away and again on every load_rom
+    pub fn set_persistence_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.persistence_dir = Some(dir.into());
+        self.restore_flags();
+    }
+
+    fn flags_path(&self) -> Option<PathBuf> {
+        let dir = self.persistence_dir.as_ref()?;
+        let hash = self.rom_hash?;
+        Some(dir.join(format!("{:016x}.flags", hash)))
+    }
+
+    pub(crate) fn restore_flags(&mut self) {
+        let Some(path) = self.flags_path() else {
+            return;
+        };
+        // the flags belong to the ROM, so another ROM's never carry over, and no file
+        // yet just means this ROM never saved anything
+        self.rpl_flags = [0; 16];
+        if let Ok(data) = fs::read(&path) {
+            let len = data.len().min(self.rpl_flags.len());
+            self.rpl_flags[..len].copy_from_slice(&data[..len]);
+        }
+    }
+
+    pub(crate) fn persist_flags(&self) {
+        let Some(path) = self.flags_path() else {
+            return;
+        };
+        if let Err(err) = fs::write(&path, self.rpl_flags) {
+            eprintln!("Could not save flags to {}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+// interpreters the quirk presets are modeled after
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    CosmacVip, // original 1977 interpreter
+    Chip48,    // HP48 calculators, what most modern ROMs expect
+    SuperChip, // SCHIP 1.1
+}
+
+impl Platform {
+    pub const ALL: [Platform; 3] = [Platform::CosmacVip, Platform::Chip48, Platform::SuperChip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::CosmacVip => "cosmac-vip",
+            Platform::Chip48 => "chip-48",
+            Platform::SuperChip => "superchip",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Platform> {
+        Platform::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Platform::CosmacVip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                vf_reset: true,
+                clip_sprites: true,
+                superchip_opcodes: false,
+                vf_accumulate_collisions: false,
+            },
+            Platform::Chip48 => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                vf_reset: false,
+                clip_sprites: true,
+                superchip_opcodes: false,
+                vf_accumulate_collisions: false,
+            },
+            Platform::SuperChip => Quirks {
+                superchip_opcodes: true,
+                ..Platform::Chip48.quirks()
+            },
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// behaviours that differ between interpreters, default matches what this emulator always did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    pub shift_uses_vy: bool, // 8XY6/8XYE shift Vy into Vx instead of shifting Vx in place
+    pub load_store_increments_i: bool, // FX55/FX65 leave I pointing past the last register
+    pub jump_uses_vx: bool,  // BNNN acts as BXNN and jumps to XNN + Vx
+    pub vf_reset: bool,      // 8XY1/8XY2/8XY3 clear VF
+    pub clip_sprites: bool,  // sprites are cut off at the screen edge instead of wrapping
+    pub superchip_opcodes: bool, // hires mode and the other SUPER-CHIP instructions are decoded
+    pub vf_accumulate_collisions: bool, // DXYN only ever sets VF, collisions add up over draws
+}
+
+impl Quirks {
+    pub const NAMES: [&'static str; 7] = [
+        "shift_uses_vy",
+        "load_store_increments_i",
+        "jump_uses_vx",
+        "vf_reset",
+        "clip_sprites",
+        "superchip_opcodes",
+        "vf_accumulate_collisions",
+    ];
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "shift_uses_vy" => Some(self.shift_uses_vy),
+            "load_store_increments_i" => Some(self.load_store_increments_i),
+            "jump_uses_vx" => Some(self.jump_uses_vx),
+            "vf_reset" => Some(self.vf_reset),
+            "clip_sprites" => Some(self.clip_sprites),
+            "superchip_opcodes" => Some(self.superchip_opcodes),
+            "vf_accumulate_collisions" => Some(self.vf_accumulate_collisions),
+            _ => None,
+        }
+    }
+
+    // returns false if there is no quirk with that name
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let flag = match name {
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "load_store_increments_i" => &mut self.load_store_increments_i,
+            "jump_uses_vx" => &mut self.jump_uses_vx,
+            "vf_reset" => &mut self.vf_reset,
+            "clip_sprites" => &mut self.clip_sprites,
+            "superchip_opcodes" => &mut self.superchip_opcodes,
+            "vf_accumulate_collisions" => &mut self.vf_accumulate_collisions,
+            _ => return false,
+        };
+        *flag = enabled;
+        true
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{Chip8, FONT_GLYPH_SIZE, FONT_SET};
+
+pub const ON_COLOR: u32 = 0xFFFFFF;
+pub const OFF_COLOR: u32 = 0x000000;
+const STATS_COLOR: u32 = 0x00FF00; // green so the readout stands out from the game
+const BLOOM_PERCENT: u32 = 20; // brightness of the glow next to lit pixels
+const BLEND_PERCENT: u32 = 50; // pixels lit in only one of the blended frames
+
+impl Chip8 {
+    // minifb expects a Vec<u32> where each u32 is 0x00RRGGBB
+    // the buffer covers the current resolution, see dimensions()
+    pub fn to_rgba(&self) -> Vec<u32> {
+        let mut buffer: Vec<u32> = if self.frame_blend {
+            let prev = &self.prev_display[..self.active_display().len()];
+            self.active_display()
+                .iter()
+                .zip(prev)
+                .map(|(&p, &prev)| match (p, prev) {
+                    (1, 1) => ON_COLOR,
+                    (0, 0) => OFF_COLOR,
+                    _ => scale_color(ON_COLOR, BLEND_PERCENT),
+                })
+                .collect()
+        } else {
+            self.active_display()
+                .iter()
+                .map(|&p| if p == 1 { ON_COLOR } else { OFF_COLOR })
+                .collect()
+        };
+        if self.crt_effect {
+            self.apply_crt_effect(&mut buffer);
+        }
+        if self.show_stats {
+            self.draw_stats_overlay(&mut buffer);
+        }
+        buffer
+    }
+
+    // nearest neighbour upscale of to_rgba for front ends that can't scale themselves
+    // the buffer is (width * scale) x (height * scale), a scale of 0 is treated as 1
+    pub fn to_rgba_scaled(&self, scale: usize) -> Vec<u32> {
+        let frame = self.to_rgba();
+        let scale = scale.max(1);
+        if scale == 1 {
+            return frame;
+        }
+        let (width, _) = self.dimensions();
+        let mut buffer = Vec::with_capacity(frame.len() * scale * scale);
+        for row in frame.chunks_exact(width) {
+            let scaled_row: Vec<u32> = row
+                .iter()
+                .flat_map(|&color| std::iter::repeat_n(color, scale))
+                .collect();
+            for _ in 0..scale {
+                buffer.extend_from_slice(&scaled_row);
+            }
+        }
+        buffer
+    }
+
+    // purely cosmetic, off by default
+    pub fn set_crt_effect(&mut self, enabled: bool) {
+        self.crt_effect = enabled;
+    }
+
+    // how much darker odd scanlines get, in percent
+    pub fn set_scanline_intensity(&mut self, percent: u8) {
+        self.scanline_intensity = percent.min(100);
+    }
+
+    // faint glow on unlit pixels to the left and right of lit ones
+    pub fn set_crt_bloom(&mut self, enabled: bool) {
+        self.crt_bloom = enabled;
+    }
+
+    // averages each pixel with the frame before it, so a sprite erased and redrawn
+    // every frame shows at half brightness instead of flickering
+    pub fn set_frame_blend(&mut self, enabled: bool) {
+        self.frame_blend = enabled;
+    }
+
+    fn apply_crt_effect(&self, buffer: &mut [u32]) {
+        let (width, height) = self.dimensions();
+        if self.crt_bloom {
+            for y in 0..height {
+                for x in 0..width {
+                    let idx = y * width + x;
+                    let lit_neighbour = (x > 0 && self.display[idx - 1] == 1)
+                        || (x < width - 1 && self.display[idx + 1] == 1);
+                    if self.display[idx] == 0 && lit_neighbour {
+                        buffer[idx] = scale_color(ON_COLOR, BLOOM_PERCENT);
+                    }
+                }
+            }
+        }
+
+        let keep = 100 - self.scanline_intensity as u32;
+        for row in buffer.chunks_exact_mut(width).skip(1).step_by(2) {
+            for color in row.iter_mut() {
+                *color = scale_color(*color, keep);
+            }
+        }
+    }
+
+    fn draw_stats_overlay(&self, buffer: &mut [u32]) {
+        let stats = self.stats();
+        let (width, height) = self.dimensions();
+        let mut hud = Hud {
+            buffer,
+            width,
+            height,
+        };
+        // fps on the first line, low 32 bits of the cycle counter on the second
+        hud.draw_hex(0, 0, stats.fps as u64, 2);
+        hud.draw_hex(0, 6, stats.cycles, 8);
+    }
+}
+
+impl Chip8 {
+    // rgba buffer the size of this display marking where the other emulator's screen
+    // differs, for visual regression tests, colors are 0x00RRGGBB like to_rgba
+    pub fn display_diff_image(
+        &self,
+        other: &Chip8,
+        changed: u32,
+        same_on: u32,
+        same_off: u32,
+    ) -> Vec<u32> {
+        let (width, height) = self.dimensions();
+        let mut buffer = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                buffer.push(match (self.pixel(x, y), other.pixel(x, y)) {
+                    (true, true) => same_on,
+                    (false, false) => same_off,
+                    _ => changed,
+                });
+            }
+        }
+        buffer
+    }
+
+    // turns a rectangle of the screen back into Octo source, one `: sprite_X_Y` label per
+    // 8 pixel wide, up to 15 row tall tile, X and Y being where DXYN has to draw it
+    // the rectangle is clipped to the screen
+    pub fn export_octo_sprites(&self, x: usize, y: usize, width: usize, height: usize) -> String {
+        let (screen_w, screen_h) = self.dimensions();
+        let x_end = (x + width).min(screen_w);
+        let y_end = (y + height).min(screen_h);
+        let mut out = String::new();
+        for tile_y in (y..y_end).step_by(15) {
+            for tile_x in (x..x_end).step_by(8) {
+                out.push_str(&format!(": sprite_{}_{}\n ", tile_x, tile_y));
+                for row in tile_y..(tile_y + 15).min(y_end) {
+                    let mut byte = 0u8;
+                    for col in tile_x..(tile_x + 8).min(x_end) {
+                        if self.pixel(col, row) {
+                            byte |= 0x80 >> (col - tile_x);
+                        }
+                    }
+                    out.push_str(&format!(" 0x{:02X}", byte));
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+// phosphor style afterglow for front ends that draw intensities instead of on/off,
+// pixels turned off dim a little on every apply instead of going dark at once
+#[derive(Debug, Clone, Default)]
+pub struct PhosphorFade {
+    levels: Vec<u8>, // 0-255 per pixel as of the last apply
+}
+
+impl PhosphorFade {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // lit pixels are 255, unlit ones lose decay from what they had, a display of
+    // a different size (resolution switch) starts over from the display itself
+    pub fn apply(&mut self, display: &[u8], decay: u8) -> &[u8] {
+        if self.levels.len() != display.len() {
+            self.levels = vec![0; display.len()];
+        }
+        for (level, &pixel) in self.levels.iter_mut().zip(display) {
+            *level = if pixel == 1 {
+                u8::MAX
+            } else {
+                level.saturating_sub(decay)
+            };
+        }
+        &self.levels
+    }
+}
+
+// scales each channel of a 0x00RRGGBB color to percent of its brightness
+fn scale_color(color: u32, percent: u32) -> u32 {
+    let channel = |shift: u32| ((color >> shift) & 0xFF) * percent / 100;
+    (channel(16) << 16) | (channel(8) << 8) | channel(0)
+}
+
+// an rgba buffer to draw text over
+struct Hud<'a> {
+    buffer: &'a mut [u32],
+    width: usize,
+    height: usize,
+}
+
+impl Hud<'_> {
+    // draws `digits` hex digits of value using the built in 4x5 font glyphs
+    fn draw_hex(&mut self, x: usize, y: usize, value: u64, digits: usize) {
+        for d in 0..digits {
+            let shift = (digits - 1 - d) * 4;
+            let glyph = ((value >> shift) & 0xF) as usize;
+            let glyph_x = x + d * 5; // 4 pixels wide plus a gap
+            for row in 0..FONT_GLYPH_SIZE {
+                let bits = FONT_SET[glyph * FONT_GLYPH_SIZE + row];
+                for col in 0..4 {
+                    let px = glyph_x + col;
+                    let py = y + row;
+                    if px < self.width && py < self.height && (bits & (0x80 >> col)) != 0 {
+                        self.buffer[px + py * self.width] = STATS_COLOR;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// tiles the rgba output of several emulators left to right, handy for comparing quirk settings
+// the result is as wide as all displays together and as tall as the tallest one
+pub fn render_side_by_side(chips: &[&Chip8]) -> Vec<u32> {
+    let width: usize = chips.iter().map(|chip| chip.dimensions().0).sum();
+    let height = chips
+        .iter()
+        .map(|chip| chip.dimensions().1)
+        .max()
+        .unwrap_or(0);
+    let mut buffer = vec![OFF_COLOR; width * height];
+    let mut x_offset = 0;
+    for chip in chips {
+        let (chip_width, _) = chip.dimensions();
+        let frame = chip.to_rgba();
+        for (y, row) in frame.chunks_exact(chip_width).enumerate() {
+            let start = y * width + x_offset;
+            buffer[start..start + chip_width].copy_from_slice(row);
+        }
+        x_offset += chip_width;
+    }
+    buffer
+}
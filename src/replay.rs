@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::{Chip8, Chip8Error, InputScript, fnv1a};
+
+const HEADER: &str = "c8replay 1";
+
+// everything needed to play a session back exactly: the seed, the ROM itself and the
+// scripted input, saved as one `.c8replay` text file, e.g.
+//
+// c8replay 1
+// seed 00000000000004d2
+// rom_hash 0075e976a244881d
+// rom 00e0a22a600c6108d01f
+// frames 120
+// frame 30: press 5
+//
+// the ROM is embedded as hex so the file works without the original, rom_hash guards
+// against a bundle that was edited by hand, the input lines are an InputScript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub rom: Vec<u8>,
+    pub frames: u64, // how many frames the session ran
+    pub script: InputScript,
+}
+
+impl Replay {
+    pub fn parse(text: &str) -> Result<Replay, Chip8Error> {
+        let invalid = |line: usize, reason: String| {
+            Chip8Error::InvalidReplay(format!("line {}: {}", line, reason))
+        };
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()));
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(invalid(1, format!("expected `{}`", HEADER))),
+        }
+
+        let (mut seed, mut rom_hash, mut rom, mut frames) = (None, None, None, None);
+        let mut script_text = String::new();
+        for (line, entry) in lines {
+            let (key, value) = entry.split_once(' ').unwrap_or((entry, ""));
+            let value = value.trim();
+            let hex_u64 = || {
+                u64::from_str_radix(value, 16)
+                    .map_err(|_| invalid(line, format!("'{}' is not a hex number", value)))
+            };
+            match key {
+                "seed" => seed = Some(hex_u64()?),
+                "rom_hash" => rom_hash = Some(hex_u64()?),
+                "rom" => {
+                    rom = Some(parse_hex_bytes(value).map_err(|reason| invalid(line, reason))?)
+                }
+                "frames" => {
+                    let not_a_count =
+                        |_| invalid(line, format!("'{}' is not a frame count", value));
+                    frames = Some(value.parse::<u64>().map_err(not_a_count)?);
+                }
+                // everything else is input, InputScript reports its own errors
+                _ => {
+                    script_text.push_str(entry);
+                    script_text.push('\n');
+                }
+            }
+        }
+
+        let missing = |field: &str| Chip8Error::InvalidReplay(format!("missing `{}`", field));
+        let rom = rom.ok_or_else(|| missing("rom"))?;
+        let rom_hash = rom_hash.ok_or_else(|| missing("rom_hash"))?;
+        if fnv1a(&rom) != rom_hash {
+            return Err(Chip8Error::InvalidReplay(
+                "embedded ROM doesn't match rom_hash".to_string(),
+            ));
+        }
+        Ok(Replay {
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            rom,
+            frames: frames.ok_or_else(|| missing("frames"))?,
+            script: InputScript::parse(&script_text)?,
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Chip8Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string()).map_err(|err| {
+            Chip8Error::InvalidReplay(format!("could not write {}: {}", path.display(), err))
+        })
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "seed {:016x}", self.seed)?;
+        writeln!(f, "rom_hash {:016x}", fnv1a(&self.rom))?;
+        write!(f, "rom ")?;
+        for byte in &self.rom {
+            write!(f, "{:02x}", byte)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "frames {}", self.frames)?;
+        write!(f, "{}", self.script)
+    }
+}
+
+fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err("ROM hex has an odd number of digits".to_string());
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|start| {
+            let pair = text.get(start..start + 2).unwrap_or("");
+            u8::from_str_radix(pair, 16).map_err(|_| format!("'{}' is not a hex byte", pair))
+        })
+        .collect()
+}
+
+impl Chip8 {
+    // cold boots, seeds the rng and loads the bundled ROM, then runs the recorded frames
+    // with the recorded input, settings like quirks are the emulator's own
+    pub fn play_replay(&mut self, replay: &Replay) -> Result<(), Chip8Error> {
+        self.prepare_replay(replay)?;
+        self.run_scripted(&replay.script, replay.frames)
+    }
+
+    fn prepare_replay(&mut self, replay: &Replay) -> Result<(), Chip8Error> {
+        self.cold_boot();
+        self.set_seed(replay.seed);
+        self.try_load_rom(&replay.rom)
+    }
+
+    // reads a .c8replay file and sets the emulator up like play_replay with the recorded
+    // input queued but doesn't run it, each run_frame after this plays the next frame
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_replay(&mut self, path: impl AsRef<std::path::Path>) -> Result<Replay, Chip8Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|err| {
+            Chip8Error::InvalidReplay(format!("could not read {}: {}", path.display(), err))
+        })?;
+        let replay = Replay::parse(&text)?;
+        self.prepare_replay(&replay)?;
+        self.queue_input(&replay.script);
+        Ok(replay)
+    }
+}
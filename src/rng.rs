@@ -0,0 +1,65 @@
+// small PRNG for CXNN whose whole state is one u64, so save states can store it
+// SplitMix64: https://prng.di.unimi.it/splitmix64.c
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8 // top bits are the best mixed
+    }
+}
+
+// the COSMAC VIP interpreter's CXNN routine, R9 is its seed register:
+//   INC R9; GLO R9; PLO RE; GHI R3; PHI RE  RE points at byte R9.0 of the interpreter page
+//   GHI R9; SEX RE; ADD; STR R6             VX = R9.1 + page byte
+//   SHRC; SEX R6; ADD; PHI R9; STR R6       VX = R9.1 = VX + (VX >> 1 with the carry on top)
+//   LDA R5; AND; STR R6                     VX &= NN, op_cxnn does this part
+// the VIP's interrupt routine also does INC R9 once per 60Hz tick, see tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VipRandom {
+    r9: u16,
+}
+
+impl VipRandom {
+    // only the low 16 bits of the seed are used
+    pub fn new(seed: u64) -> Self {
+        VipRandom { r9: seed as u16 }
+    }
+
+    pub fn state(&self) -> u16 {
+        self.r9
+    }
+
+    pub fn tick(&mut self) {
+        self.r9 = self.r9.wrapping_add(1);
+    }
+
+    // page is the 256 bytes the interpreter's own code sat in on the VIP, 0x100-0x1FF
+    pub fn next_u8(&mut self, page: &[u8]) -> u8 {
+        self.tick();
+        let [high, low] = self.r9.to_be_bytes();
+        let (vx, carry) = high.overflowing_add(page[low as usize]);
+        let shifted = (vx >> 1) | ((carry as u8) << 7);
+        let high = shifted.wrapping_add(vx);
+        self.r9 = u16::from_be_bytes([high, low]);
+        high
+    }
+}
@@ -0,0 +1,14 @@
+// demo ROMs compiled into the binary so the web build doesn't need to fetch files
+pub const BUILTIN_ROMS: &[(&str, &[u8])] = &[
+    ("pong", include_bytes!("../roms/Pong.ch8")),
+    ("ibm_logo", include_bytes!("../roms/IBM_Logo.ch8")), // static picture, checks DXYN
+    ("maze", include_bytes!("../roms/Maze.ch8")),         // random diagonals, checks CXNN
+    ("keypad", include_bytes!("../roms/Keypad.ch8")),     // shows the hex digit of each key pressed
+];
+
+pub fn builtin_rom(name: &str) -> Option<&'static [u8]> {
+    BUILTIN_ROMS
+        .iter()
+        .find(|(rom_name, _)| *rom_name == name)
+        .map(|(_, data)| *data)
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::{Chip8, Chip8Error};
+
+// one key change in an input script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptEvent {
+    pub frame: u64, // applied right before this frame runs, the first frame is 0
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// key presses for headless runs written by hand, e.g.
+// `frame 30: press 1; frame 45: release 1`
+// entries are separated by `;` or newlines, keys are hex 0-F and `#` starts a comment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    events: Vec<ScriptEvent>, // sorted by frame, same frame keeps the written order
+}
+
+impl InputScript {
+    pub fn parse(text: &str) -> Result<InputScript, Chip8Error> {
+        let mut events = Vec::new();
+        let entries = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty());
+        for (idx, entry) in entries.enumerate() {
+            events.push(
+                parse_entry(entry).map_err(|reason| Chip8Error::InvalidInputScript {
+                    entry: idx + 1,
+                    reason,
+                })?,
+            );
+        }
+        events.sort_by_key(|event| event.frame);
+        Ok(InputScript { events })
+    }
+
+    pub fn events(&self) -> &[ScriptEvent] {
+        &self.events
+    }
+
+    // the frame the last event happens in, 0 for an empty script
+    pub fn last_frame(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.frame)
+    }
+}
+
+// one entry per line in the format parse reads
+impl fmt::Display for InputScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            let action = if event.pressed { "press" } else { "release" };
+            writeln!(f, "frame {}: {} {:X}", event.frame, action, event.key)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_entry(entry: &str) -> Result<ScriptEvent, String> {
+    let expected = || format!("expected `frame <n>: press|release <key>`, got '{}'", entry);
+    let (frame, action) = entry.split_once(':').ok_or_else(expected)?;
+    let frame = frame
+        .trim()
+        .strip_prefix("frame")
+        .ok_or_else(expected)?
+        .trim();
+    let frame: u64 = frame
+        .parse()
+        .map_err(|_| format!("'{}' is not a frame number", frame))?;
+
+    let (action, key) = action.trim().split_once(' ').ok_or_else(expected)?;
+    let pressed = match action {
+        "press" => true,
+        "release" => false,
+        _ => return Err(format!("unknown action '{}'", action)),
+    };
+    let key = key.trim();
+    let key = u8::from_str_radix(key, 16)
+        .ok()
+        .filter(|&k| k < 16)
+        .ok_or_else(|| format!("'{}' is not a chip-8 key 0-F", key))?;
+    Ok(ScriptEvent {
+        frame,
+        key,
+        pressed,
+    })
+}
+
+impl Chip8 {
+    // runs `frames` frames without a window, pressing and releasing keys as scripted
+    pub fn run_scripted(&mut self, script: &InputScript, frames: u64) -> Result<(), Chip8Error> {
+        self.queue_input(script);
+        for _ in 0..frames {
+            self.run_frame()?;
+        }
+        Ok(())
+    }
+
+    // hands the script to run_frame, the next run_frame is frame 0, replaces whatever
+    // was queued before
+    pub fn queue_input(&mut self, script: &InputScript) {
+        self.queued_input = script.events().iter().copied().collect();
+        self.input_frame = 0;
+    }
+
+    // presses and releases the keys scripted for the frame about to run
+    pub(crate) fn apply_queued_input(&mut self) {
+        while let Some(event) = self.queued_input.front().copied() {
+            if event.frame > self.input_frame {
+                break;
+            }
+            self.queued_input.pop_front();
+            self.set_key(event.key, event.pressed);
+        }
+        self.input_frame += 1;
+    }
+}
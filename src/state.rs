@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::rng::{SplitMix64, VipRandom};
+use crate::{Chip8, Chip8Error, HI_HEIGHT, HI_WIDTH};
+
+// save state layout, all numbers little endian:
+// "C8SS" | version u8 | ram 4096 | pc u16 | i u16 | vx 16 | hires u8 | display 8192
+// | stack size u16 | stack entries u16 each | sp u16 | delay u8 | sound u8 | rng state u64 | cycles u64
+// | vip rng state u16 | rng frozen u8 | rng replay length u32 | replay bytes | rpl flags 16
+// | timer accum u128 | cycle accum u128 | timer cycle accum u64
+// only machine state is saved, settings like quirks stay as the emulator has them
+// the stack size comes back with the state since the saved entries have to fit, and the
+// rng and timer leftovers do so CXNN and the timers carry on exactly where they were
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u8 = 4;
+
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.ram);
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        out.extend_from_slice(&self.i.to_le_bytes());
+        out.extend_from_slice(&self.vx);
+        out.push(self.hires as u8);
+        out.extend_from_slice(&self.display);
+        out.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        for entry in &self.stack {
+            out.extend_from_slice(&entry.to_le_bytes());
+        }
+        out.extend_from_slice(&self.sp.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+        out.extend_from_slice(&self.cycles.to_le_bytes());
+        out.extend_from_slice(&self.vip_rng.state().to_le_bytes());
+        out.push(self.rng_frozen as u8);
+        out.extend_from_slice(&(self.rng_replay.len() as u32).to_le_bytes());
+        out.extend(self.rng_replay.iter());
+        out.extend_from_slice(&self.rpl_flags);
+        out.extend_from_slice(&self.timer_accum.to_le_bytes());
+        out.extend_from_slice(&self.cycle_accum.to_le_bytes());
+        out.extend_from_slice(&self.timer_cycle_accum.to_le_bytes());
+        out
+    }
+
+    // on error the emulator is left untouched
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        let mut reader = Reader { data, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(Chip8Error::InvalidSaveState("not a save state".to_string()));
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(Chip8Error::InvalidSaveState(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+
+        // parse everything first so a bad state can't leave us half restored
+        let mut ram = [0u8; 4096];
+        ram.copy_from_slice(reader.take(4096)?);
+        let pc = reader.u16()?;
+        let i = reader.u16()?;
+        let mut vx = [0u8; 16];
+        vx.copy_from_slice(reader.take(16)?);
+        let hires = reader.u8()? != 0;
+        let mut display = [0u8; HI_WIDTH * HI_HEIGHT];
+        display.copy_from_slice(reader.take(HI_WIDTH * HI_HEIGHT)?);
+        let stack_size = reader.u16()? as usize;
+        let mut stack = vec![0u16; stack_size];
+        for entry in stack.iter_mut() {
+            *entry = reader.u16()?;
+        }
+        let sp = reader.u16()?;
+        if sp as usize > stack_size {
+            return Err(Chip8Error::InvalidSaveState(format!(
+                "stack pointer {} is past the {} entry stack",
+                sp, stack_size
+            )));
+        }
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let rng_state = reader.u64()?;
+        let cycles = reader.u64()?;
+        let vip_rng_state = reader.u16()?;
+        let rng_frozen = reader.u8()? != 0;
+        let replay_len = reader.u32()? as usize;
+        let rng_replay: VecDeque<u8> = reader.take(replay_len)?.iter().copied().collect();
+        let mut rpl_flags = [0u8; 16];
+        rpl_flags.copy_from_slice(reader.take(16)?);
+        let timer_accum = reader.u128()?;
+        let cycle_accum = reader.u128()?;
+        let timer_cycle_accum = reader.u64()?;
+        if reader.pos != data.len() {
+            return Err(Chip8Error::InvalidSaveState(
+                "trailing bytes after save state".to_string(),
+            ));
+        }
+
+        self.ram = ram;
+        self.pc = pc;
+        self.i = i;
+        self.vx = vx;
+        self.hires = hires;
+        self.display = display;
+        self.stack = stack;
+        self.sp = sp;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.rng = SplitMix64::new(rng_state);
+        self.cycles = cycles;
+        self.vip_rng = VipRandom::new(vip_rng_state as u64);
+        self.rng_frozen = rng_frozen;
+        self.rng_replay = rng_replay;
+        self.rpl_flags = rpl_flags;
+        self.timer_accum = timer_accum;
+        self.cycle_accum = cycle_accum;
+        self.timer_cycle_accum = timer_cycle_accum;
+        self.draw_flag = true; // whatever is on screen now is stale
+        self.invalidate_instruction_cache();
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        let end = self.pos + len;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or_else(|| Chip8Error::InvalidSaveState("save state is truncated".to_string()))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Chip8Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Chip8Error> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn u128(&mut self) -> Result<u128, Chip8Error> {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(self.take(16)?);
+        Ok(u128::from_le_bytes(bytes))
+    }
+}
@@ -1,39 +1,167 @@
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 
-use crate::Chip8;
+use crate::render::PhosphorFade;
+use crate::roms::{BUILTIN_ROMS, builtin_rom};
+use crate::{Chip8, ManualClock, Platform};
+
+// what step_and_describe executed, shows up in JS as an object with getters
+#[wasm_bindgen]
+pub struct StepDescription {
+    pc: u16,
+    opcode: u16,
+    mnemonic: String,
+}
+
+#[wasm_bindgen]
+impl StepDescription {
+    #[wasm_bindgen(getter)]
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn mnemonic(&self) -> String {
+        self.mnemonic.clone()
+    }
+}
 
 #[wasm_bindgen]
 pub struct Chip8Wasm {
     inner: Chip8,
+    frame_buffer: Vec<u8>, // reused by frame_ptr so JS reads wasm memory instead of a new array
+    clock: ManualClock,    // moved by tick_with_dt, so timing goes through update() like native
+    fade: PhosphorFade,    // afterglow levels behind frame_faded
 }
 
 #[wasm_bindgen]
 impl Chip8Wasm {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Chip8Wasm {
-        Chip8Wasm { inner: Chip8::new() }
+        let clock = ManualClock::new();
+        let mut inner = Chip8::new();
+        inner.set_clock(Box::new(clock.clone()));
+        let _ = inner.update(); // starts the clock
+        Chip8Wasm {
+            inner,
+            frame_buffer: Vec::new(),
+            clock,
+            fade: PhosphorFade::new(),
+        }
     }
 
     pub fn load_pong(&mut self) {
-        self.inner.load_rom(include_bytes!("../roms/Pong.ch8"));
+        self.load_builtin("pong");
+    }
+
+    pub fn available_roms(&self) -> Vec<String> {
+        BUILTIN_ROMS.iter().map(|(name, _)| name.to_string()).collect()
+    }
+
+    // false if there is no builtin ROM with that name
+    pub fn load_builtin(&mut self, name: &str) -> bool {
+        match builtin_rom(name) {
+            Some(data) => {
+                self.inner.cold_boot();
+                self.inner.load_rom(data);
+                true
+            }
+            None => false,
+        }
     }
 
     pub fn set_key(&mut self, key: u8, pressed: bool) {
-        let idx = key as usize;
-        if idx < self.inner.keypad.len() {
-            self.inner.keypad[idx] = pressed;
+        self.inner.set_key(key, pressed);
+    }
+
+    // advances emulation by dt_ms of real time, call it with the performance.now()
+    // difference since the last frame, the same clock driven stepping native run() uses
+    pub fn tick_with_dt(&mut self, dt_ms: f64) {
+        self.clock
+            .advance(Duration::from_secs_f64(dt_ms.max(0.0) / 1000.0));
+        let _ = self.inner.update();
+    }
+
+    // returns false for an unknown platform name
+    pub fn set_platform(&mut self, name: &str) -> bool {
+        match Platform::from_name(name) {
+            Some(platform) => {
+                self.inner.set_platform(platform);
+                true
+            }
+            None => false,
         }
     }
 
+    pub fn set_quirk(&mut self, name: &str, enabled: bool) -> bool {
+        self.inner.set_quirk(name, enabled)
+    }
+
+    pub fn quirk_report(&self) -> String {
+        self.inner.quirk_report()
+    }
+
     pub fn tick(&mut self) {
-        for _ in 0..10 {
-            let opcode = self.inner.fetch();
-            self.inner.decode_execute(opcode);
+        // the web build never turns on strict mode so unknown opcodes are just skipped
+        let _ = self.inner.run_frame();
+    }
+
+    // runs a single instruction for the step debugger, undefined if it failed
+    pub fn step_and_describe(&mut self) -> JsValue {
+        match self.inner.step_info() {
+            Ok(info) => StepDescription {
+                pc: info.pc,
+                opcode: info.opcode,
+                mnemonic: info.mnemonic,
+            }
+            .into(),
+            Err(_) => JsValue::UNDEFINED,
         }
-        self.inner.tick_timers();
     }
 
+    // one byte per pixel for the current resolution, see width() and height()
     pub fn frame(&self) -> Vec<u8> {
-        self.inner.display.to_vec()
+        self.inner.active_display().to_vec()
+    }
+
+    // refreshes the internal frame buffer and returns where it lives in wasm memory,
+    // read frame_len() bytes from there, the pointer can change when the resolution does
+    pub fn frame_ptr(&mut self) -> *const u8 {
+        let (width, height) = self.inner.dimensions();
+        self.frame_buffer.resize(width * height, 0);
+        let _ = self.inner.frame_into(&mut self.frame_buffer);
+        self.frame_buffer.as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame_buffer.len()
+    }
+
+    // like frame() but with an intensity of 0-255 per pixel, turned off pixels fade by
+    // decay on each call, call it once per animation frame and use the value as alpha
+    pub fn frame_faded(&mut self, decay: u8) -> Vec<u8> {
+        self.fade.apply(self.inner.active_display(), decay).to_vec()
+    }
+
+    // lets the JS loop skip repainting the canvas when nothing changed
+    pub fn needs_redraw(&self) -> bool {
+        self.inner.needs_redraw()
+    }
+
+    pub fn mark_drawn(&mut self) {
+        self.inner.mark_drawn();
+    }
+
+    pub fn width(&self) -> usize {
+        self.inner.dimensions().0
+    }
+
+    pub fn height(&self) -> usize {
+        self.inner.dimensions().1
     }
 }
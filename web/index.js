@@ -1,13 +1,12 @@
 import init, { Chip8Wasm } from "../pkg/chip8_emulator.js";
 
-const WIDTH = 64;
-const HEIGHT = 32;
-
 const canvas = document.getElementById("screen");
+const statusBar = document.getElementById("status");
+const romSelect = document.getElementById("rom");
 const ctx = canvas.getContext("2d", { alpha: false });
 ctx.imageSmoothingEnabled = false;
 
-const imageData = ctx.createImageData(WIDTH, HEIGHT);
+let imageData = ctx.createImageData(canvas.width, canvas.height);
 const keyMap = {
   KeyW: 0x1,
   KeyQ: 0x4,
@@ -16,6 +15,7 @@ const keyMap = {
 };
 
 let chip8 = null;
+let wasmMemory = null;
 
 function handleKey(event, pressed) {
   if (!chip8) {
@@ -33,6 +33,12 @@ window.addEventListener("keydown", (event) => handleKey(event, true));
 window.addEventListener("keyup", (event) => handleKey(event, false));
 
 function render(frameBuffer) {
+  // SUPER-CHIP games can switch between 64x32 and 128x64
+  if (chip8.width() !== canvas.width || chip8.height() !== canvas.height) {
+    canvas.width = chip8.width();
+    canvas.height = chip8.height();
+    imageData = ctx.createImageData(canvas.width, canvas.height);
+  }
   for (let i = 0; i < frameBuffer.length; i++) {
     const value = frameBuffer[i] ? 255 : 0;
     const base = i * 4;
@@ -44,17 +50,36 @@ function render(frameBuffer) {
   ctx.putImageData(imageData, 0, 0);
 }
 
-function loop() {
-  chip8.tick();
-  render(chip8.frame());
+let lastTime = null;
+
+// timestamps keep the game at the same speed whatever the monitor refresh rate is
+function loop(now) {
+  if (lastTime !== null) {
+    chip8.tick_with_dt(now - lastTime);
+  }
+  lastTime = now;
+  if (chip8.needs_redraw()) {
+    const ptr = chip8.frame_ptr();
+    render(new Uint8Array(wasmMemory.buffer, ptr, chip8.frame_len()));
+    chip8.mark_drawn();
+  }
   requestAnimationFrame(loop);
 }
 
 async function start() {
-  await init();
+  const wasm = await init();
+  wasmMemory = wasm.memory;
   chip8 = new Chip8Wasm();
+  for (const name of chip8.available_roms()) {
+    romSelect.add(new Option(name, name));
+  }
+  romSelect.addEventListener("change", () => {
+    chip8.load_builtin(romSelect.value);
+    romSelect.blur();
+  });
   chip8.load_pong();
-  loop();
+  statusBar.textContent = chip8.quirk_report();
+  requestAnimationFrame(loop);
 }
 
 start();